@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::client::{ConsulXClient, KvPair};
+use crate::errors::ClientResult;
+use crate::export::{ExportEntry, ImportReport};
+
+/// Bumped whenever the archive layout changes incompatibly. Version 2
+/// checksums the whole archive rather than only its entries.
+pub const BACKUP_FORMAT_VERSION: u32 = 2;
+
+/// Where a backup was taken from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupSource {
+    pub addr: String,
+    pub dc: Option<String>,
+}
+
+/// An exported entry plus the indexes it had when the backup was taken.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupEntry {
+    #[serde(flatten)]
+    pub entry: ExportEntry,
+    pub create_index: u64,
+    pub modify_index: u64,
+    pub lock_index: u64,
+}
+
+impl From<KvPair> for BackupEntry {
+    fn from(p: KvPair) -> Self {
+        let (create_index, modify_index, lock_index) = (p.create_index, p.modify_index, p.lock_index);
+        Self {
+            entry: ExportEntry::from(p),
+            create_index,
+            modify_index,
+            lock_index,
+        }
+    }
+}
+
+/// A versioned, checksummed snapshot of a KV subtree. Stored on disk as
+/// gzip-compressed JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    /// Unix timestamp (seconds) when the backup was taken.
+    pub created_at: u64,
+    pub source: BackupSource,
+    pub prefix: String,
+    pub entries: Vec<BackupEntry>,
+    /// Hex SHA-256 of the JSON-serialized archive, this field left out.
+    pub checksum: String,
+}
+
+impl Backup {
+    pub fn new(source: BackupSource, prefix: &str, entries: Vec<BackupEntry>) -> serde_json::Result<Self> {
+        let mut backup = Self {
+            version: BACKUP_FORMAT_VERSION,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+            source,
+            prefix: prefix.to_string(),
+            entries,
+            checksum: String::new(),
+        };
+        backup.checksum = checksum(&backup)?;
+        Ok(backup)
+    }
+
+    /// Check the format version and that the archive (prefix, source and
+    /// timestamp included) matches the checksum.
+    pub fn verify(&self) -> Result<()> {
+        if self.version != BACKUP_FORMAT_VERSION {
+            anyhow::bail!(
+                "unsupported backup format version {} (expected {BACKUP_FORMAT_VERSION})",
+                self.version
+            );
+        }
+        let actual = checksum(self)?;
+        if actual != self.checksum {
+            anyhow::bail!("backup checksum mismatch: archive is corrupt or was modified");
+        }
+        Ok(())
+    }
+
+    pub fn write_to(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(path.as_ref())
+            .with_context(|| format!("creating {}", path.as_ref().display()))?;
+        let mut gz = GzEncoder::new(BufWriter::new(file), Compression::default());
+        serde_json::to_writer(&mut gz, self)?;
+        gz.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Read and [`verify`](Self::verify) an archive.
+    pub fn read_from(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref())
+            .with_context(|| format!("opening {}", path.as_ref().display()))?;
+        let backup: Backup = serde_json::from_reader(GzDecoder::new(BufReader::new(file)))
+            .context("not a consulx backup archive")?;
+        backup.verify()?;
+        Ok(backup)
+    }
+}
+
+fn checksum(backup: &Backup) -> serde_json::Result<String> {
+    let mut doc = serde_json::to_value(backup)?;
+    if let Some(fields) = doc.as_object_mut() {
+        fields.remove("checksum");
+    }
+    let digest = Sha256::digest(serde_json::to_vec(&doc)?);
+    Ok(digest.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// What a restore would do, by key. Keys that exist in Consul but not in
+/// the backup are never touched.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RestorePlan {
+    pub create: Vec<String>,
+    pub overwrite: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+impl RestorePlan {
+    /// Compare `backup` against the `current` pairs under its prefix.
+    pub fn new(backup: &Backup, current: &[KvPair]) -> Self {
+        let current: HashMap<&str, &KvPair> = current.iter().map(|p| (p.key.as_str(), p)).collect();
+        let mut plan = RestorePlan::default();
+
+        for e in &backup.entries {
+            let key = e.entry.key.clone();
+            match current.get(key.as_str()) {
+                None => plan.create.push(key),
+                Some(p) if p.flags == e.entry.flags && p.value.as_deref().unwrap_or_default() == e.entry.value => {
+                    plan.unchanged.push(key)
+                }
+                Some(_) => plan.overwrite.push(key),
+            }
+        }
+        plan
+    }
+
+    pub fn is_noop(&self) -> bool {
+        self.create.is_empty() && self.overwrite.is_empty()
+    }
+}
+
+impl ConsulXClient {
+    /// Snapshot every key under `prefix` with its metadata. The source is
+    /// the agent that answered, as configured (e.g. `unix:///...`).
+    pub async fn kv_backup(&self, prefix: &str) -> ClientResult<Backup> {
+        let pairs = self.kv_get_recurse(prefix).await?;
+        let source = BackupSource {
+            addr: self.address(),
+            dc: self.dc.clone(),
+        };
+        Ok(Backup::new(source, prefix, pairs.into_iter().map(BackupEntry::from).collect())?)
+    }
+
+    /// Work out what restoring `backup` would change right now.
+    pub async fn kv_restore_plan(&self, backup: &Backup) -> ClientResult<RestorePlan> {
+        let current = self.kv_get_recurse(&backup.prefix).await?;
+        Ok(RestorePlan::new(backup, &current))
+    }
+
+    /// Write the created/overwritten keys of `plan` from `backup`, in
+    /// transaction chunks like [`kv_import`](Self::kv_import).
+    pub async fn kv_restore(&self, backup: &Backup, plan: &RestorePlan) -> ClientResult<ImportReport> {
+        let wanted: std::collections::HashSet<&str> = plan
+            .create
+            .iter()
+            .chain(&plan.overwrite)
+            .map(String::as_str)
+            .collect();
+        let entries: Vec<ExportEntry> = backup
+            .entries
+            .iter()
+            .filter(|e| wanted.contains(e.entry.key.as_str()))
+            .map(|e| e.entry.clone())
+            .collect();
+        self.kv_import(&entries).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_agent;
+    use crate::errors::ClientError;
+
+    fn entry(key: &str, value: &str) -> BackupEntry {
+        BackupEntry {
+            entry: ExportEntry { key: key.into(), flags: 0, value: value.as_bytes().to_vec() },
+            create_index: 1,
+            modify_index: 2,
+            lock_index: 0,
+        }
+    }
+
+    fn pair(key: &str, value: &str) -> KvPair {
+        KvPair {
+            key: key.into(),
+            value: Some(value.as_bytes().to_vec()),
+            create_index: 1,
+            modify_index: 1,
+            lock_index: 0,
+            flags: 0,
+            session: None,
+        }
+    }
+
+    fn sample() -> Backup {
+        let source = BackupSource { addr: "http://127.0.0.1:8500".into(), dc: None };
+        Backup::new(source, "app/", vec![entry("app/a", "1"), entry("app/b", "2"), entry("app/c", "3")])
+            .unwrap()
+    }
+
+    #[test]
+    fn archive_round_trips_through_gzip() {
+        let backup = sample();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("backup.cxb");
+        backup.write_to(&path).unwrap();
+        let read = Backup::read_from(&path).unwrap();
+        assert_eq!(read, backup);
+    }
+
+    #[test]
+    fn tampered_entries_fail_verification() {
+        let mut backup = sample();
+        backup.entries[0].entry.value = b"evil".to_vec();
+        let err = backup.verify().unwrap_err();
+        assert!(err.to_string().contains("checksum"));
+    }
+
+    #[test]
+    fn tampered_metadata_fails_verification() {
+        let mut backup = sample();
+        backup.prefix = "other/".into();
+        assert!(backup.verify().unwrap_err().to_string().contains("checksum"));
+
+        let mut backup = sample();
+        backup.source.dc = Some("dc9".into());
+        assert!(backup.verify().is_err());
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let mut backup = sample();
+        backup.version = 99;
+        assert!(backup.verify().is_err());
+    }
+
+    #[test]
+    fn plan_splits_create_overwrite_unchanged() {
+        let current = vec![pair("app/a", "1"), pair("app/b", "changed"), pair("app/extra", "x")];
+        let plan = RestorePlan::new(&sample(), &current);
+        assert_eq!(plan.create, vec!["app/c".to_string()]);
+        assert_eq!(plan.overwrite, vec!["app/b".to_string()]);
+        assert_eq!(plan.unchanged, vec!["app/a".to_string()]);
+        assert!(!plan.is_noop());
+    }
+
+    #[tokio::test]
+    async fn backup_errors_are_typed() {
+        let agent = test_agent::spawn(vec![(403, "Permission denied")]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+
+        let err = client.kv_backup("app/").await.unwrap_err();
+        assert!(matches!(err, ClientError::PermissionDenied { status: 403, .. }), "{err:?}");
+        let err = client.kv_restore_plan(&sample()).await.unwrap_err();
+        assert!(matches!(err, ClientError::PermissionDenied { .. }), "{err:?}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket_backup_records_the_socket() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::UnixListener;
+
+        let dir = tempfile::tempdir().unwrap();
+        let sock = dir.path().join("consul.sock");
+        let listener = UnixListener::bind(&sock).unwrap();
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = conn.read(&mut buf).await.unwrap();
+            let body = r#"[{"Key":"app/a","Value":"MQ==","Flags":0,"CreateIndex":1,"ModifyIndex":2,"LockIndex":0}]"#;
+            let resp = format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{body}", body.len());
+            conn.write_all(resp.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        });
+
+        let addr = format!("unix://{}", sock.display());
+        let client = ConsulXClient::with_options(&addr, None, None).unwrap();
+        let backup = client.kv_backup("app/").await.unwrap();
+        assert_eq!(backup.source, BackupSource { addr, dc: None });
+        assert_eq!(backup.entries.len(), 1);
+        assert!(server.await.unwrap().starts_with("GET /v1/kv/app/?recurse"));
+    }
+}
@@ -0,0 +1,172 @@
+use colored::Colorize;
+use std::io::BufRead;
+
+use crate::client::ConsulXClient;
+use crate::commands::ScriptGuard;
+use crate::errors::{ConsulXError, Result};
+use crate::parser::parse;
+use crate::repl::ReplState;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BatchOptions {
+    /// Keep going after a failing line instead of stopping at the first one.
+    pub continue_on_error: bool,
+}
+
+/// Outcome of a script run. Failures carry their 1-based line number.
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    pub succeeded: usize,
+    pub failed: Vec<(usize, ConsulXError)>,
+    /// True if the run stopped before the end of the script.
+    pub stopped_early: bool,
+}
+
+impl BatchSummary {
+    /// Exit code of the first failure, or 0 if every line succeeded.
+    pub fn exit_code(&self) -> i32 {
+        self.failed.first().map_or(0, |(_, e)| e.exit_code())
+    }
+}
+
+/// Run REPL commands line by line from `reader`. Blank lines and lines
+/// starting with `#` are skipped; `exit`/`quit` ends the script. State
+/// carries across lines as in the REPL, so `begin`/`commit` work too.
+/// Commands that would prompt fail instead; scripts pass `--yes`.
+pub async fn run_script<R: BufRead>(
+    client: &ConsulXClient,
+    reader: R,
+    opts: BatchOptions,
+) -> Result<BatchSummary> {
+    let _no_prompts = ScriptGuard::new();
+    let mut state = ReplState::default();
+    let mut summary = BatchSummary::default();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line?;
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if trimmed.eq_ignore_ascii_case("exit") || trimmed.eq_ignore_ascii_case("quit") {
+            break;
+        }
+
+        let result = match parse(trimmed) {
+            Ok(cmd) => state.run(client, cmd).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => summary.succeeded += 1,
+            Err(e) => {
+                eprintln!("{} {}", format!("line {line_no}:").red(), e);
+                summary.failed.push((line_no, e));
+                if !opts.continue_on_error {
+                    summary.stopped_early = true;
+                    break;
+                }
+            }
+        }
+    }
+
+    if let Some(n) = state.discard_pending() {
+        eprintln!("{} transaction left open, {n} queued ops discarded", "Warning:".yellow());
+    }
+    state.release_locks().await;
+
+    Ok(summary)
+}
+
+/// Print the end-of-run tally to stderr, leaving stdout for command output.
+pub fn print_summary(summary: &BatchSummary) {
+    let status = format!(
+        "{} succeeded, {} failed",
+        summary.succeeded,
+        summary.failed.len()
+    );
+    if summary.failed.is_empty() {
+        eprintln!("{}", status.green());
+    } else {
+        eprintln!("{}", status.red());
+        if summary.stopped_early {
+            eprintln!("stopped at line {} (use --continue-on-error to keep going)", summary.failed[0].0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Nothing here reaches the network: put/del inside `begin` are only queued.
+    fn client() -> ConsulXClient {
+        ConsulXClient::with_options("http://127.0.0.1:1", None, None).unwrap()
+    }
+
+    const SCRIPT: &str = "\
+# seed data
+begin
+
+put app/a 1
+put app/b 2
+frobnicate
+abort
+";
+
+    #[tokio::test]
+    async fn stops_at_first_failure() {
+        let summary = run_script(&client(), SCRIPT.as_bytes(), BatchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(summary.succeeded, 3);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, 6);
+        assert!(summary.stopped_early);
+        assert_eq!(summary.exit_code(), 2);
+    }
+
+    #[tokio::test]
+    async fn continue_on_error_runs_everything() {
+        let opts = BatchOptions { continue_on_error: true };
+        let summary = run_script(&client(), SCRIPT.as_bytes(), opts).await.unwrap();
+        assert_eq!(summary.succeeded, 4);
+        assert_eq!(summary.failed.len(), 1);
+        assert!(!summary.stopped_early);
+    }
+
+    #[tokio::test]
+    async fn prompts_fail_instead_of_reading_the_script() {
+        let agent = crate::client::test_agent::spawn(vec![(200, r#"["app/a"]"#)]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+        let script = "del -r app/\nbegin\n";
+        let summary = run_script(&client, script.as_bytes(), BatchOptions::default()).await.unwrap();
+        assert_eq!(summary.failed.len(), 1);
+        assert!(summary.failed[0].1.to_string().contains("pass --yes"), "{}", summary.failed[0].1);
+        // listed the keys, but never deleted them
+        assert_eq!(agent.requests(), ["GET /v1/kv/app/?keys"]);
+    }
+
+    #[tokio::test]
+    async fn recursive_delete_is_queued_in_a_transaction() {
+        let script = "begin\ndel -r app/\ndel -r /\nabort\n";
+        let opts = BatchOptions { continue_on_error: true };
+        let summary = run_script(&client(), script.as_bytes(), opts).await.unwrap();
+        assert_eq!(summary.succeeded, 3);
+        assert_eq!(summary.failed.len(), 1);
+        assert_eq!(summary.failed[0].0, 3);
+    }
+
+    #[tokio::test]
+    async fn exit_ends_the_script() {
+        let script = "begin\nexit\nfrobnicate\n";
+        let summary = run_script(&client(), script.as_bytes(), BatchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(summary.succeeded, 1);
+        assert!(summary.failed.is_empty());
+        assert_eq!(summary.exit_code(), 0);
+    }
+}
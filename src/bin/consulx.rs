@@ -1,14 +1,72 @@
-use consulx::{start_repl, ConsulXClient};
+use consulx::batch::{self, BatchOptions};
+use consulx::cli::{self, USAGE};
+use consulx::commands::execute;
+use consulx::parser::parse;
+use consulx::{start_repl, ConsulXClientBuilder};
+use std::io::{BufRead, BufReader, IsTerminal};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let addr = std::env::var("CONSUL_HTTP_ADDR")
-        .unwrap_or_else(|_| "http://127.0.0.1:8500".to_string());
+    let args = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(e.exit_code());
+        }
+    };
+    if args.help {
+        println!("{USAGE}");
+        return Ok(());
+    }
 
-    println!("Connecting to Consul at: {}", addr);
+    // Flags win over the environment, matching the official consul CLI.
+    let mut builder = ConsulXClientBuilder::from_env()?;
+    if let Some(addr) = &args.addr {
+        builder = builder.address(addr);
+    }
+    if let Some(token) = &args.token {
+        builder = builder.token(Some(token.clone()));
+    }
+    if let Some(dc) = &args.dc {
+        builder = builder.dc(Some(dc.clone()));
+    }
+    let client = builder.build()?;
+    let addr = client.address();
 
-    let client = ConsulXClient::new(&addr)?;
-    start_repl(client).await?;
+    // A script comes from -f, or from stdin when it is piped and no
+    // one-shot command was given.
+    let script: Option<Box<dyn BufRead>> = match args.file.as_deref() {
+        Some("-") => Some(Box::new(std::io::stdin().lock())),
+        Some(path) => Some(Box::new(BufReader::new(std::fs::File::open(path)?))),
+        None if args.command.is_empty() && !std::io::stdin().is_terminal() => {
+            Some(Box::new(std::io::stdin().lock()))
+        }
+        None => None,
+    };
+    if let Some(reader) = script {
+        let opts = BatchOptions { continue_on_error: args.continue_on_error };
+        let summary = batch::run_script(&client, reader, opts).await?;
+        batch::print_summary(&summary);
+        std::process::exit(summary.exit_code());
+    }
+
+    let Some(line) = args.command_line() else {
+        println!("Connecting to Consul at: {}", addr);
+        start_repl(client).await?;
+        return Ok(());
+    };
+
+    let result = match parse(&line) {
+        Ok(cmd) => execute(&client, cmd).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("{e}");
+        if let Some(hint) = e.hint() {
+            eprintln!("hint: {hint}");
+        }
+        std::process::exit(e.exit_code());
+    }
 
     Ok(())
 }
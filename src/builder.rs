@@ -0,0 +1,327 @@
+use anyhow::Result;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Client, Url};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client::ConsulXClient;
+use crate::endpoint::{Endpoint, Endpoints};
+use crate::retry::{
+    CONNECT_TIMEOUT_ENV, REQUEST_TIMEOUT_ENV, RETRY_ATTEMPTS_ENV, RETRY_BACKOFF_ENV, RetryPolicy, parse_duration,
+};
+use crate::tls::{TlsOptions, parse_bool};
+
+/// Address used when neither the builder nor `CONSUL_HTTP_ADDR` sets one.
+pub const DEFAULT_ADDR: &str = "http://127.0.0.1:8500";
+
+/// Base URL for requests sent over a Unix socket. The host is never
+/// resolved; only the path and query reach the agent.
+const UNIX_SOCKET_BASE: &str = "http://localhost";
+
+/// Read a non-empty environment variable.
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn env_duration(name: &str) -> Result<Option<Duration>> {
+    env_var(name)
+        .map(|v| parse_duration(&v).ok_or_else(|| anyhow::anyhow!("{name}: invalid duration '{v}' (e.g. 500ms, 5s)")))
+        .transpose()
+}
+
+/// Configures a [`ConsulXClient`]: agent address(es), ACL token,
+/// datacenter, TLS, retry policy and timeouts.
+///
+/// ```no_run
+/// # fn demo() -> anyhow::Result<()> {
+/// use consulx::{ConsulXClientBuilder, RetryPolicy};
+/// use std::time::Duration;
+///
+/// let client = ConsulXClientBuilder::from_env()?
+///     .retry_policy(RetryPolicy { max_attempts: 5, ..RetryPolicy::default() })
+///     .request_timeout(Duration::from_secs(10))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConsulXClientBuilder {
+    addrs: Vec<String>,
+    token: Option<String>,
+    dc: Option<String>,
+    retry: RetryPolicy,
+    connect_timeout: Option<Duration>,
+    request_timeout: Option<Duration>,
+    tls: TlsOptions,
+    https: bool,
+}
+
+impl Default for ConsulXClientBuilder {
+    fn default() -> Self {
+        Self::new(DEFAULT_ADDR)
+    }
+}
+
+impl ConsulXClientBuilder {
+    /// A builder for `addr` with default retries and timeouts, ignoring
+    /// the environment.
+    pub fn new(addr: &str) -> Self {
+        Self {
+            addrs: split_addrs(addr),
+            token: None,
+            dc: None,
+            retry: RetryPolicy::default(),
+            connect_timeout: Some(Duration::from_secs(5)),
+            request_timeout: Some(Duration::from_secs(30)),
+            tls: TlsOptions::default(),
+            https: false,
+        }
+    }
+
+    /// Start from the environment: `CONSUL_HTTP_ADDR`, `CONSUL_HTTP_TOKEN`,
+    /// `CONSUL_DATACENTER`/`CONSUL_DC`, `CONSUL_HTTP_SSL`, the TLS variables
+    /// read by [`TlsOptions::from_env`], plus `CONSULX_RETRY_ATTEMPTS`,
+    /// `CONSULX_RETRY_BACKOFF`, `CONSULX_CONNECT_TIMEOUT` and
+    /// `CONSULX_REQUEST_TIMEOUT`. Later builder calls override these.
+    pub fn from_env() -> Result<Self> {
+        let mut builder = Self::new(&env_var("CONSUL_HTTP_ADDR").unwrap_or_else(|| DEFAULT_ADDR.to_string()));
+        builder.token = env_var("CONSUL_HTTP_TOKEN");
+        builder.dc = env_var("CONSUL_DATACENTER").or_else(|| env_var("CONSUL_DC"));
+        builder.tls = TlsOptions::from_env()?;
+        if let Some(v) = env_var("CONSUL_HTTP_SSL") {
+            builder.https = parse_bool(&v)
+                .ok_or_else(|| anyhow::anyhow!("CONSUL_HTTP_SSL: expected true or false, got '{v}'"))?;
+        }
+
+        if let Some(v) = env_var(RETRY_ATTEMPTS_ENV) {
+            builder.retry.max_attempts = v
+                .parse::<u32>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| anyhow::anyhow!("{RETRY_ATTEMPTS_ENV}: expected a positive number, got '{v}'"))?;
+        }
+        if let Some(d) = env_duration(RETRY_BACKOFF_ENV)? {
+            builder.retry.base_delay = d;
+        }
+        if let Some(d) = env_duration(CONNECT_TIMEOUT_ENV)? {
+            builder.connect_timeout = Some(d);
+        }
+        if let Some(d) = env_duration(REQUEST_TIMEOUT_ENV)? {
+            builder.request_timeout = Some(d);
+        }
+        Ok(builder)
+    }
+
+    /// `http(s)://host:port`, a bare `host:port`, or `unix:///path/to.sock`.
+    /// A comma-separated list sets several agents, as for
+    /// [`addresses`](Self::addresses).
+    pub fn address(mut self, addr: &str) -> Self {
+        self.addrs = split_addrs(addr);
+        self
+    }
+
+    /// Agents to fail over between, in order of preference. Requests stick
+    /// to one until it stops answering or returns 5xx.
+    pub fn addresses<I, S>(mut self, addrs: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.addrs = addrs.into_iter().flat_map(|a| split_addrs(a.as_ref())).collect();
+        self
+    }
+
+    pub fn token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    pub fn dc(mut self, dc: Option<String>) -> Self {
+        self.dc = dc;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for a whole request. Blocking queries get their wait time
+    /// added, so watches aren't cut short.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn tls(mut self, tls: TlsOptions) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Use `https` for an address given without a scheme
+    /// (`CONSUL_HTTP_SSL`).
+    pub fn https(mut self, https: bool) -> Self {
+        self.https = https;
+        self
+    }
+
+    pub fn build(self) -> Result<ConsulXClient> {
+        let mut headers = HeaderMap::new();
+        if let Some(t) = self.token.as_deref() {
+            let mut value = HeaderValue::from_str(t)
+                .map_err(|_| anyhow::anyhow!("CONSUL_HTTP_TOKEN contains invalid header characters"))?;
+            value.set_sensitive(true);
+            headers.insert("X-Consul-Token", value);
+        }
+
+        if self.addrs.is_empty() {
+            anyhow::bail!("no Consul address given");
+        }
+        let endpoints = self
+            .addrs
+            .iter()
+            .map(|addr| self.endpoint(addr, &headers))
+            .collect::<Result<Vec<_>>>()?;
+        let (http, base) = (endpoints[0].http.clone(), endpoints[0].base.clone());
+
+        Ok(ConsulXClient {
+            http,
+            base,
+            dc: self.dc,
+            retry: self.retry,
+            request_timeout: self.request_timeout,
+            endpoints: Arc::new(Endpoints::new(endpoints)),
+        })
+    }
+
+    fn endpoint(&self, addr: &str, headers: &HeaderMap) -> Result<Endpoint> {
+        let unix_socket = match addr.strip_prefix("unix://") {
+            Some("") => anyhow::bail!("unix:// address has no socket path"),
+            Some(path) => Some(PathBuf::from(path)),
+            None => None,
+        };
+        let base = if unix_socket.is_some() {
+            UNIX_SOCKET_BASE.to_string()
+        } else if addr.contains("://") {
+            addr.to_string()
+        } else if self.https {
+            format!("https://{addr}")
+        } else {
+            format!("http://{addr}")
+        };
+        // normalized, so requests built against one endpoint can be moved
+        // to another by swapping the prefix
+        let base = Url::parse(&base)
+            .map_err(|e| anyhow::anyhow!("invalid Consul address '{addr}': {e}"))?
+            .as_str()
+            .trim_end_matches('/')
+            .to_string();
+
+        // reqwest's default features also pull in native-tls; stay on rustls.
+        let mut http = self.tls.apply(Client::builder().use_rustls_tls().default_headers(headers.clone()))?;
+        if let Some(path) = &unix_socket {
+            http = use_unix_socket(http, path.clone())?;
+        }
+        if let Some(t) = self.connect_timeout {
+            http = http.connect_timeout(t);
+        }
+        // The per-request timeout is applied in ConsulXClient::send, where
+        // blocking queries can extend it.
+
+        Ok(Endpoint::new(base, unix_socket, http.build()?))
+    }
+}
+
+/// Split a comma-separated address list, dropping blanks.
+fn split_addrs(addrs: &str) -> Vec<String> {
+    addrs
+        .split(',')
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(unix)]
+fn use_unix_socket(http: reqwest::ClientBuilder, path: PathBuf) -> Result<reqwest::ClientBuilder> {
+    Ok(http.unix_socket(path))
+}
+
+#[cfg(not(unix))]
+fn use_unix_socket(_: reqwest::ClientBuilder, _: PathBuf) -> Result<reqwest::ClientBuilder> {
+    anyhow::bail!("unix:// addresses are only supported on Unix")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_sets_retry_and_timeouts() {
+        let client = ConsulXClientBuilder::new("http://consul:8500/")
+            .dc(Some("dc2".into()))
+            .retry_policy(RetryPolicy::none())
+            .request_timeout(Duration::from_secs(3))
+            .build()
+            .unwrap();
+        assert_eq!(client.base, "http://consul:8500");
+        assert_eq!(client.dc.as_deref(), Some("dc2"));
+        assert_eq!(client.retry.max_attempts, 1);
+        assert_eq!(client.request_timeout, Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn bare_addresses_get_a_scheme() {
+        let base = |b: ConsulXClientBuilder| b.build().unwrap().base;
+        assert_eq!(base(ConsulXClientBuilder::new("10.0.0.5:8500")), "http://10.0.0.5:8500");
+        assert_eq!(base(ConsulXClientBuilder::new("10.0.0.5:8501").https(true)), "https://10.0.0.5:8501");
+        assert_eq!(base(ConsulXClientBuilder::new("http://10.0.0.5:8500").https(true)), "http://10.0.0.5:8500");
+    }
+
+    #[test]
+    fn address_lists_become_endpoints_in_order() {
+        let client = ConsulXClientBuilder::new("http://10.0.0.1:8500, 10.0.0.2:8500,,unix:///run/consul.sock")
+            .build()
+            .unwrap();
+        let addrs: Vec<String> = client.endpoints().iter().map(|e| e.address()).collect();
+        assert_eq!(addrs, ["http://10.0.0.1:8500", "http://10.0.0.2:8500", "unix:///run/consul.sock"]);
+        assert_eq!(client.base, "http://10.0.0.1:8500");
+        assert!(ConsulXClientBuilder::new(" , ").build().is_err());
+    }
+
+    #[test]
+    fn unix_addresses_route_through_the_socket() {
+        let client = ConsulXClientBuilder::new("unix:///var/run/consul.sock").dc(Some("dc1".into())).build().unwrap();
+        assert_eq!(client.endpoints()[0].unix_socket, Some(PathBuf::from("/var/run/consul.sock")));
+        assert_eq!(client.address(), "unix:///var/run/consul.sock");
+        assert!(ConsulXClientBuilder::new("unix://").build().is_err());
+    }
+
+    #[test]
+    fn tls_server_name_keeps_each_address() {
+        // nothing is resolved up front, so an unreachable agent doesn't stop the build
+        let tls = TlsOptions { server_name: Some("server.dc1.consul".into()), ..TlsOptions::default() };
+        let client = ConsulXClientBuilder::new("https://consul-a.invalid:8501,https://127.0.0.1:8501")
+            .tls(tls)
+            .build()
+            .unwrap();
+        let addrs: Vec<String> = client.endpoints().iter().map(|e| e.address()).collect();
+        assert_eq!(addrs, ["https://consul-a.invalid:8501", "https://127.0.0.1:8501"]);
+    }
+
+    #[test]
+    fn token_must_be_a_valid_header() {
+        let err = ConsulXClientBuilder::new(DEFAULT_ADDR)
+            .token(Some("bad\ntoken".into()))
+            .build()
+            .err()
+            .expect("token with a newline is rejected");
+        assert!(err.to_string().contains("CONSUL_HTTP_TOKEN"));
+    }
+}
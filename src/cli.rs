@@ -0,0 +1,205 @@
+use std::borrow::Cow;
+
+use crate::errors::{ConsulXError, Result};
+
+pub const USAGE: &str = "\
+Usage: consulx [--addr <url>] [--token <token>] [--dc <dc>] [<command> [args...]]
+       consulx [global flags] -f <script> [--continue-on-error]
+
+--addr (like CONSUL_HTTP_ADDR) takes a comma-separated list of agents to
+fail over between.
+
+Without a command, starts the interactive REPL. With one, runs it and exits:
+  consulx get app/db
+  consulx put app/db '{\"host\":\"db\"}'
+  consulx tree app/
+
+Scripts (`-f file`, `-f -` or piped stdin) run one REPL command per line;
+`#` starts a comment. Execution stops at the first failing line unless
+--continue-on-error is given. Confirmation prompts fail there; pass --yes.
+
+Exit codes: 0 ok, 1 not found, 2 usage error, 3 server error,
+4 permission denied, 5 CAS conflict or rolled-back txn, 6 Consul unreachable,
+7 rate limited";
+
+/// Global flags plus the words of a one-shot command (empty for the REPL).
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CliArgs {
+    pub addr: Option<String>,
+    pub token: Option<String>,
+    pub dc: Option<String>,
+    pub help: bool,
+    /// Script to run (`-` for stdin).
+    pub file: Option<String>,
+    pub continue_on_error: bool,
+    pub command: Vec<String>,
+}
+
+/// Commands whose last argument is raw JSON, read verbatim to the end of
+/// the line, so it must not be quoted.
+const VERBATIM_TAIL: &[&str] = &["put-json", "set-json", "patch"];
+
+impl CliArgs {
+    /// Rebuild the command words into a line for `parser::parse`, quoting
+    /// words the shell kept whole (spaces, quotes) so they stay one word.
+    pub fn command_line(&self) -> Option<String> {
+        let (cmd, args) = self.command.split_first()?;
+        let verbatim_tail = VERBATIM_TAIL.contains(&cmd.to_lowercase().as_str());
+
+        let mut line = cmd.clone();
+        for (i, arg) in args.iter().enumerate() {
+            line.push(' ');
+            if verbatim_tail && i + 1 == args.len() {
+                line.push_str(arg);
+            } else {
+                line.push_str(&quote_word(arg));
+            }
+        }
+        Some(line)
+    }
+}
+
+/// Wrap `word` in quotes the parser strips again, if it needs them.
+fn quote_word(word: &str) -> Cow<'_, str> {
+    let needs_quotes = word.is_empty() || word.chars().any(|c| c.is_whitespace() || c == '"' || c == '\'');
+    if !needs_quotes {
+        return Cow::Borrowed(word);
+    }
+    let q = if word.contains('\'') { '"' } else { '\'' };
+    Cow::Owned(format!("{q}{word}{q}"))
+}
+
+/// Parse `std::env::args().skip(1)`. Global flags are only recognised
+/// before the command word, so command arguments pass through untouched.
+pub fn parse_args<I>(args: I) -> Result<CliArgs>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut out = CliArgs::default();
+    let mut it = args.into_iter();
+
+    while let Some(arg) = it.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((f, v)) if f.starts_with("--") => (f.to_string(), Some(v.to_string())),
+            _ => (arg.clone(), None),
+        };
+
+        let slot = match flag.as_str() {
+            "--addr" => &mut out.addr,
+            "--token" => &mut out.token,
+            "--dc" => &mut out.dc,
+            "-f" | "--file" => &mut out.file,
+            "--continue-on-error" => {
+                out.continue_on_error = true;
+                continue;
+            }
+            "-h" | "--help" => {
+                out.help = true;
+                continue;
+            }
+            f if f.starts_with('-') && f.len() > 1 => {
+                return Err(ConsulXError::InvalidArgument(format!("unknown flag '{f}'")));
+            }
+            _ => {
+                out.command.push(arg);
+                out.command.extend(it);
+                break;
+            }
+        };
+
+        let value = match inline {
+            Some(v) => v,
+            None => it.next().ok_or(ConsulXError::MissingArgument("flag value"))?,
+        };
+        *slot = Some(value);
+    }
+
+    if let (Some(file), Some(cmd)) = (&out.file, out.command.first()) {
+        return Err(ConsulXError::InvalidArgument(format!(
+            "-f {file} can't be combined with the command '{cmd}'; put it in the script instead"
+        )));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(v: &[&str]) -> Result<CliArgs> {
+        parse_args(v.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn no_args_means_repl() {
+        let a = args(&[]).unwrap();
+        assert_eq!(a, CliArgs::default());
+        assert_eq!(a.command_line(), None);
+    }
+
+    #[test]
+    fn global_flags_before_command() {
+        let a = args(&["--addr", "http://c:8500", "--token=abc", "--dc", "dc2", "get", "app/db"]).unwrap();
+        assert_eq!(a.addr.as_deref(), Some("http://c:8500"));
+        assert_eq!(a.token.as_deref(), Some("abc"));
+        assert_eq!(a.dc.as_deref(), Some("dc2"));
+        assert_eq!(a.command_line().as_deref(), Some("get app/db"));
+    }
+
+    #[test]
+    fn flags_after_command_belong_to_it() {
+        let a = args(&["put", "--cas", "5", "k", "v"]).unwrap();
+        assert_eq!(a.command_line().as_deref(), Some("put --cas 5 k v"));
+        assert_eq!(a.addr, None);
+    }
+
+    #[test]
+    fn whitespace_and_quotes_in_values_survive() {
+        use crate::parser::{Command, parse};
+
+        let line = args(&["put", "app/msg", "hello  world"]).unwrap().command_line().unwrap();
+        assert!(matches!(parse(&line).unwrap(), Command::Put { value, .. } if value == "hello  world"));
+
+        let line = args(&["put", "app/name", "\"quoted\""]).unwrap().command_line().unwrap();
+        assert!(matches!(parse(&line).unwrap(), Command::Put { value, .. } if value == "\"quoted\""));
+
+        let line = args(&["put-json", "app/db", r#"{"host": "db internal"}"#]).unwrap().command_line().unwrap();
+        assert!(matches!(parse(&line).unwrap(), Command::PutJson { json, .. } if json == r#"{"host": "db internal"}"#));
+
+        let line = args(&["grep", "-i", "two words", "app/"]).unwrap().command_line().unwrap();
+        assert!(matches!(parse(&line).unwrap(), Command::Grep { pattern, .. } if pattern == "two words"));
+    }
+
+    #[test]
+    fn script_and_command_are_exclusive() {
+        let err = args(&["-f", "seed.cx", "get", "app/db"]).unwrap_err();
+        assert!(matches!(err, ConsulXError::InvalidArgument(_)), "{err}");
+        assert_eq!(err.exit_code(), 2);
+    }
+
+    #[test]
+    fn missing_flag_value_and_unknown_flag() {
+        assert!(matches!(
+            args(&["--addr"]),
+            Err(ConsulXError::MissingArgument(_))
+        ));
+        assert!(matches!(
+            args(&["--bogus", "get", "k"]),
+            Err(ConsulXError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn script_flags() {
+        let a = args(&["-f", "seed.cx", "--continue-on-error"]).unwrap();
+        assert_eq!(a.file.as_deref(), Some("seed.cx"));
+        assert!(a.continue_on_error);
+        assert_eq!(a.command_line(), None);
+    }
+
+    #[test]
+    fn help_flag() {
+        assert!(args(&["--help"]).unwrap().help);
+        assert!(args(&["-h"]).unwrap().help);
+    }
+}
@@ -1,8 +1,20 @@
-use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use percent_encoding::{AsciiSet, CONTROLS, utf8_percent_encode};
-use reqwest::Client;
-use reqwest::header::{HeaderMap, HeaderValue};
-use serde::{Serialize, de::DeserializeOwned};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::builder::ConsulXClientBuilder;
+use crate::endpoint::{Endpoint, Endpoints};
+use crate::errors::{ClientError, ClientResult as Result};
+use crate::retry::{Idempotency, RetryPolicy};
+
+/// How long a blocking query waits for a change before Consul answers
+/// with the unchanged index.
+pub const WATCH_WAIT: Duration = Duration::from_secs(10);
 
 /// Characters we percent-encode inside a KV key. Consul keys are path
 /// segments, so `/` is deliberately preserved as a separator.
@@ -22,51 +34,293 @@ const KEY_ENCODE_SET: &AsciiSet = &CONTROLS
     .add(b'\\');
 
 
+/// Result of a check-and-set write. Consul answers a `?cas=` request with a
+/// bare `true`/`false` body rather than an error status, so a lost race is
+/// reported here instead of through `Err`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CasOutcome {
+    Applied,
+    Conflict,
+}
+
+impl CasOutcome {
+    fn from_body(body: &str) -> Self {
+        if body.trim() == "true" {
+            CasOutcome::Applied
+        } else {
+            CasOutcome::Conflict
+        }
+    }
+
+    pub fn is_applied(self) -> bool {
+        self == CasOutcome::Applied
+    }
+}
+
+
+/// A KV entry with its metadata, as returned by `GET /v1/kv/<key>`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct KvPair {
+    pub key: String,
+    /// Decoded value bytes; `None` when the key holds no value at all.
+    #[serde(default, deserialize_with = "de_base64")]
+    pub value: Option<Vec<u8>>,
+    pub create_index: u64,
+    pub modify_index: u64,
+    pub lock_index: u64,
+    pub flags: u64,
+    #[serde(default)]
+    pub session: Option<String>,
+}
+
+impl KvPair {
+    /// The value as UTF-8 text (lossy), or `None` if the key has no value.
+    pub fn value_str(&self) -> Option<String> {
+        self.value
+            .as_deref()
+            .map(|v| String::from_utf8_lossy(v).into_owned())
+    }
+}
+
+/// Consul sends `Value` base64-encoded (or `null`); decode it on the way in.
+fn de_base64<'de, D>(de: D) -> std::result::Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(de)? {
+        Some(s) => BASE64
+            .decode(s.as_bytes())
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+
+/// Typed entries loaded from a prefix, plus the keys whose values could not
+/// be deserialized (only populated by the lenient loader).
+#[derive(Debug)]
+pub struct JsonListing<T> {
+    pub entries: Vec<(String, T)>,
+    pub failures: Vec<(String, serde_json::Error)>,
+}
+
+/// Deserialize each pair's value as JSON, skipping keys with no or blank
+/// values (folders). With `lenient`, bad entries are recorded, not fatal.
+fn decode_json_pairs<T>(pairs: Vec<KvPair>, lenient: bool) -> Result<JsonListing<T>>
+where
+    T: DeserializeOwned,
+{
+    let mut listing = JsonListing {
+        entries: Vec::new(),
+        failures: Vec::new(),
+    };
+
+    for pair in pairs {
+        let Some(raw) = pair.value.as_deref() else {
+            continue;
+        };
+        if raw.trim_ascii().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_slice::<T>(raw) {
+            Ok(parsed) => listing.entries.push((pair.key, parsed)),
+            Err(e) if lenient => listing.failures.push((pair.key, e)),
+            Err(e) => {
+                return Err(ClientError::Decode(format!("invalid JSON at '{}': {e}", pair.key)));
+            }
+        }
+    }
+
+    Ok(listing)
+}
+
+
+/// Read the blocking-query cursor from `X-Consul-Index` (0 if absent).
+fn consul_index(resp: &reqwest::Response) -> u64 {
+    resp.headers()
+        .get("x-consul-index")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("0")
+        .parse::<u64>()
+        .unwrap_or(0)
+}
+
+/// Consul blocking queries return the same `X-Consul-Index` when nothing has
+/// changed (the query simply timed out), and a *smaller* index means the
+/// table was reset — in which case we must restart from index 0. This helper
+/// applies that reconciliation and reports whether the state actually changed.
+///
+/// Right after `switched` to another agent, a smaller index only means that
+/// agent is behind, so the watch keeps its place instead of starting over.
+pub fn reconcile(prev: Option<u64>, mut new_index: u64, switched: bool) -> (u64, bool) {
+    if let Some(prev) = prev {
+        if new_index < prev {
+            if switched {
+                return (prev, false);
+            }
+            new_index = 0; // reset per Consul blocking-query guidance
+        }
+        (new_index, new_index != prev)
+    } else {
+        (new_index, true) // first observation always prints
+    }
+}
+
+/// The `Retry-After` delay, when given in seconds.
+fn retry_after(resp: &Response) -> Option<Duration> {
+    resp.headers()
+        .get(RETRY_AFTER)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Turn a non-success response into a [`ClientError`], keeping the body
+/// Consul sent (its error text) and any `Retry-After` hint.
+pub(crate) async fn error_from(resp: Response) -> ClientError {
+    let status = resp.status().as_u16();
+    let retry_after = retry_after(&resp);
+    let body = resp.text().await.unwrap_or_default();
+    ClientError::from_status(status, body, retry_after)
+}
+
+
 #[derive(Clone)]
 pub struct ConsulXClient {
+    /// Client and base URL requests are built with; [`send`](Self::send)
+    /// moves each one onto whichever endpoint is current.
     pub http: Client,
     pub base: String,
     pub dc: Option<String>,
+    pub retry: RetryPolicy,
+    /// Applied to every request; blocking queries add [`WATCH_WAIT`].
+    pub request_timeout: Option<Duration>,
+    /// Shared by clones (e.g. [`with_dc`](Self::with_dc)), so health is too.
+    pub(crate) endpoints: Arc<Endpoints>,
 }
 
 
 impl ConsulXClient {
-    /// Build a client for `base`, picking up `CONSUL_HTTP_TOKEN` and the
-    /// datacenter (`CONSUL_DATACENTER`/`CONSUL_DC`) from the environment.
-    pub fn new(base: &str) -> Result<Self> {
-        let token = std::env::var("CONSUL_HTTP_TOKEN").ok().filter(|t| !t.is_empty());
-        let dc = std::env::var("CONSUL_DATACENTER")
-            .or_else(|_| std::env::var("CONSUL_DC"))
-            .ok()
-            .filter(|d| !d.is_empty());
-        Self::with_options(base, token, dc)
+    /// Build a client for `base`, picking up the token, datacenter, retry
+    /// and timeout settings from the environment (see
+    /// [`ConsulXClientBuilder::from_env`]).
+    pub fn new(base: &str) -> anyhow::Result<Self> {
+        ConsulXClientBuilder::from_env()?.address(base).build()
     }
 
     /// Build a client with an explicit ACL token and/or datacenter.
-    pub fn with_options(base: &str, token: Option<String>, dc: Option<String>) -> Result<Self> {
-        let mut headers = HeaderMap::new();
-        if let Some(t) = token.as_deref() {
-            let mut value = HeaderValue::from_str(t)
-                .map_err(|_| anyhow::anyhow!("CONSUL_HTTP_TOKEN contains invalid header characters"))?;
-            value.set_sensitive(true);
-            headers.insert("X-Consul-Token", value);
-        }
+    pub fn with_options(base: &str, token: Option<String>, dc: Option<String>) -> anyhow::Result<Self> {
+        ConsulXClientBuilder::new(base).token(token).dc(dc).build()
+    }
+
+    pub fn builder() -> ConsulXClientBuilder {
+        ConsulXClientBuilder::default()
+    }
 
-        let http = Client::builder().default_headers(headers).build()?;
 
-        Ok(Self {
-            http,
-            base: base.trim_end_matches('/').to_string(),
-            dc,
-        })
+    /// The address of the agent currently in use, e.g. for "Connecting
+    /// to ..." output. Changes after a failover.
+    pub fn address(&self) -> String {
+        self.endpoints.current().address()
     }
 
+    /// Position in [`endpoints`](Self::endpoints) of the agent currently in
+    /// use. Unlike [`address`](Self::address) it tells agents apart even
+    /// when several share a display address.
+    pub fn current_endpoint(&self) -> usize {
+        self.endpoints.current_index()
+    }
+
+    /// Every configured agent, in failover order.
+    pub fn endpoints(&self) -> &[Endpoint] {
+        self.endpoints.list()
+    }
+
+    /// A copy of this client that targets datacenter `dc` instead.
+    pub fn with_dc(&self, dc: Option<String>) -> Self {
+        Self { dc, ..self.clone() }
+    }
 
     /// Build from CONSUL_HTTP_ADDR (or default http://127.0.0.1:8500)
-    pub fn from_env() -> Result<Self> {
-        let addr = std::env::var("CONSUL_HTTP_ADDR")
-            .unwrap_or_else(|_| "http://127.0.0.1:8500".to_string());
-        Self::new(&addr)
+    pub fn from_env() -> anyhow::Result<Self> {
+        ConsulXClientBuilder::from_env()?.build()
+    }
+
+    /// Send `req` under the retry policy and request timeout. Non-success
+    /// responses are returned as-is once retries run out.
+    ///
+    /// With several endpoints, a connection error or 5xx that the policy
+    /// would retry moves the request (and later ones) to the next healthy
+    /// endpoint straight away; backoff only starts once every endpoint has
+    /// been tried. Every try, failovers included, counts against the
+    /// policy's `max_attempts`.
+    pub(crate) async fn send(&self, req: RequestBuilder, idempotency: Idempotency) -> Result<Response> {
+        self.send_with_timeout(req, idempotency, self.request_timeout).await
+    }
+
+    /// [`send`](Self::send) for a blocking query, whose timeout is extended
+    /// by the wait (plus Consul's up-to-1/16 jitter).
+    async fn send_blocking(&self, req: RequestBuilder) -> Result<Response> {
+        let timeout = self.request_timeout.map(|t| t + WATCH_WAIT + WATCH_WAIT / 16);
+        self.send_with_timeout(req, Idempotency::Safe, timeout).await
+    }
+
+    async fn send_with_timeout(
+        &self,
+        req: RequestBuilder,
+        idempotency: Idempotency,
+        timeout: Option<Duration>,
+    ) -> Result<Response> {
+        let req = match timeout {
+            Some(t) => req.timeout(t),
+            None => req,
+        };
+        let req = req.build()?;
+
+        let mut tried = vec![false; self.endpoints.list().len()];
+        let (mut attempt, mut backoffs) = (1, 0);
+        loop {
+            // Bodies are always buffered, so this only fails for streams.
+            let Some(this_try) = req.try_clone() else {
+                return Ok(self.http.execute(req).await?);
+            };
+            let i = self.endpoints.pick(&tried, Instant::now());
+            tried[i] = true;
+            let endpoint = &self.endpoints.list()[i];
+            let result = endpoint.http.execute(endpoint.rebase(this_try, &self.base)).await;
+
+            let (endpoint_failed, retryable) = match &result {
+                Ok(resp) => (
+                    resp.status().is_server_error(),
+                    self.retry.retries_status(resp.status().as_u16(), idempotency),
+                ),
+                Err(e) => (!e.is_builder(), self.retry.retries_error(e, idempotency)),
+            };
+            if endpoint_failed {
+                self.endpoints.mark_down(i, Instant::now());
+            } else {
+                self.endpoints.mark_up(i);
+            }
+
+            if !retryable || attempt >= self.retry.max_attempts {
+                return Ok(result?);
+            }
+            attempt += 1;
+            if endpoint_failed && self.endpoints.any_untried(&tried) {
+                continue;
+            }
+
+            backoffs += 1;
+            let delay = match &result {
+                Ok(resp) => retry_after(resp).map(|d| d.min(self.retry.max_delay)),
+                Err(_) => None,
+            };
+            tokio::time::sleep(delay.unwrap_or_else(|| self.retry.jittered_delay(backoffs))).await;
+            tried.fill(false);
+        }
     }
 
     /// Build a `/v1/kv/<key>` URL, percent-encoding the key and appending
@@ -74,7 +328,13 @@ impl ConsulXClient {
     /// value is emitted as a bare flag (e.g. `raw`, `keys`).
     fn kv_url(&self, key: &str, params: &[(&str, String)]) -> String {
         let encoded = utf8_percent_encode(key, KEY_ENCODE_SET).to_string();
-        let mut url = format!("{}/v1/kv/{}", self.base, encoded);
+        self.api_url(&format!("v1/kv/{encoded}"), params)
+    }
+
+    /// Build a URL for any HTTP API `path` (already encoded, no leading
+    /// slash) with the same query handling as [`kv_url`](Self::kv_url).
+    pub(crate) fn api_url(&self, path: &str, params: &[(&str, String)]) -> String {
+        let mut url = format!("{}/{}", self.base, path);
 
         let mut query: Vec<String> = params
             .iter()
@@ -95,7 +355,7 @@ impl ConsulXClient {
     /// GET /v1/kv/<key>?raw
     pub async fn kv_get_raw(&self, key: &str) -> Result<Option<String>> {
         let url = self.kv_url(key, &[("raw", "true".into())]);
-        let resp = self.http.get(url).send().await?;
+        let resp = self.send(self.http.get(url), Idempotency::Safe).await?;
 
         if resp.status().is_success() {
             let txt = resp.text().await?;
@@ -106,37 +366,124 @@ impl ConsulXClient {
             return Ok(None);
         }
 
-        Err(anyhow::anyhow!("GET failed with status {}", resp.status()))
+        Err(error_from(resp).await)
+    }
+
+    /// GET /v1/kv/<key> — the value together with its indexes, flags and session.
+    pub async fn kv_get_entry(&self, key: &str) -> Result<Option<KvPair>> {
+        let url = self.kv_url(key, &[]);
+        let resp = self.send(self.http.get(url), Idempotency::Safe).await?;
+
+        if resp.status().is_success() {
+            let pairs = resp.json::<Vec<KvPair>>().await?;
+            return Ok(pairs.into_iter().next());
+        }
+
+        if resp.status().as_u16() == 404 {
+            return Ok(None);
+        }
+
+        Err(error_from(resp).await)
     }
 
     /// PUT /v1/kv/<key>
     pub async fn kv_put(&self, key: &str, value: &str) -> Result<()> {
+        self.kv_put_bytes(key, value.as_bytes()).await
+    }
+
+    /// PUT /v1/kv/<key> with an arbitrary (possibly non-UTF-8) value.
+    pub async fn kv_put_bytes(&self, key: &str, value: &[u8]) -> Result<()> {
         let url = self.kv_url(key, &[]);
-        let resp = self.http.put(url).body(value.to_string()).send().await?;
+        let resp = self.send(self.http.put(url).body(value.to_vec()), Idempotency::Unsafe).await?;
 
         if resp.status().is_success() {
             return Ok(());
         }
 
-        Err(anyhow::anyhow!("PUT failed with status {}", resp.status()))
+        Err(error_from(resp).await)
+    }
+
+    /// PUT /v1/kv/<key>?cas=<index>
+    ///
+    /// Only writes if the key's current `ModifyIndex` equals `modify_index`.
+    /// An index of `0` means "create only if the key does not exist".
+    pub async fn kv_put_cas(&self, key: &str, value: &str, modify_index: u64) -> Result<CasOutcome> {
+        let url = self.kv_url(key, &[("cas", modify_index.to_string())]);
+        let resp = self.send(self.http.put(url).body(value.to_string()), Idempotency::Unsafe).await?;
+
+        if resp.status().is_success() {
+            return Ok(CasOutcome::from_body(&resp.text().await?));
+        }
+
+        Err(error_from(resp).await)
+    }
+
+    /// PUT /v1/kv/<key>?acquire=<session> — `true` if the lock was taken.
+    pub async fn kv_acquire(&self, key: &str, value: &[u8], session: &str) -> Result<bool> {
+        self.kv_put_session(key, value, "acquire", session).await
+    }
+
+    /// PUT /v1/kv/<key>?release=<session> — `true` if the lock was released.
+    pub async fn kv_release(&self, key: &str, value: &[u8], session: &str) -> Result<bool> {
+        self.kv_put_session(key, value, "release", session).await
+    }
+
+    async fn kv_put_session(&self, key: &str, value: &[u8], op: &str, session: &str) -> Result<bool> {
+        let url = self.kv_url(key, &[(op, session.to_string())]);
+        let resp = self.send(self.http.put(url).body(value.to_vec()), Idempotency::Unsafe).await?;
+
+        if resp.status().is_success() {
+            return Ok(resp.text().await?.trim() == "true");
+        }
+
+        Err(error_from(resp).await)
     }
 
     /// DELETE /v1/kv/<key>
     pub async fn kv_delete(&self, key: &str) -> Result<()> {
         let url = self.kv_url(key, &[]);
-        let resp = self.http.delete(url).send().await?;
+        let resp = self.send(self.http.delete(url), Idempotency::Unsafe).await?;
+
+        if resp.status().is_success() {
+            return Ok(());
+        }
+
+        Err(error_from(resp).await)
+    }
+
+    /// DELETE /v1/kv/<prefix>?recurse — removes every key under `prefix`.
+    /// An empty (or all-`/`) prefix is refused, since it would wipe the store.
+    pub async fn kv_delete_tree(&self, prefix: &str) -> Result<()> {
+        if prefix.trim_matches('/').is_empty() {
+            return Err(ClientError::InvalidRequest("refusing to recursively delete an empty prefix".into()));
+        }
+
+        let url = self.kv_url(prefix, &[("recurse", String::new())]);
+        let resp = self.send(self.http.delete(url), Idempotency::Unsafe).await?;
 
         if resp.status().is_success() {
             return Ok(());
         }
 
-        Err(anyhow::anyhow!("DELETE failed with status {}", resp.status()))
+        Err(error_from(resp).await)
+    }
+
+    /// DELETE /v1/kv/<key>?cas=<index>
+    pub async fn kv_delete_cas(&self, key: &str, modify_index: u64) -> Result<CasOutcome> {
+        let url = self.kv_url(key, &[("cas", modify_index.to_string())]);
+        let resp = self.send(self.http.delete(url), Idempotency::Unsafe).await?;
+
+        if resp.status().is_success() {
+            return Ok(CasOutcome::from_body(&resp.text().await?));
+        }
+
+        Err(error_from(resp).await)
     }
 
     /// LIST /v1/kv/<prefix>?keys
     pub async fn kv_list(&self, prefix: &str) -> Result<Vec<String>> {
         let url = self.kv_url(prefix, &[("keys", String::new())]);
-        let resp = self.http.get(url).send().await?;
+        let resp = self.send(self.http.get(url), Idempotency::Safe).await?;
 
         if resp.status().is_success() {
             let keys = resp.json::<Vec<String>>().await?;
@@ -150,7 +497,25 @@ impl ConsulXClient {
             return Ok(vec![]);
         }
 
-        Err(anyhow::anyhow!("LIST failed with status {}", resp.status()))
+        Err(error_from(resp).await)
+    }
+
+    /// GET /v1/kv/<prefix>?recurse — every pair under a prefix in one round trip.
+    pub async fn kv_get_recurse(&self, prefix: &str) -> Result<Vec<KvPair>> {
+        let url = self.kv_url(prefix, &[("recurse", String::new())]);
+        let resp = self.send(self.http.get(url), Idempotency::Safe).await?;
+
+        if resp.status().is_success() {
+            let pairs = resp.json::<Vec<KvPair>>().await?;
+            return Ok(pairs);
+        }
+
+        // Same as kv_list: an empty prefix 404s, other statuses are errors.
+        if resp.status().as_u16() == 404 {
+            return Ok(vec![]);
+        }
+
+        Err(error_from(resp).await)
     }
 
     /// High-level: fetch a value and deserialize JSON into type T
@@ -159,7 +524,8 @@ impl ConsulXClient {
         T: DeserializeOwned,
     {
         if let Some(raw) = self.kv_get_raw(key).await? {
-            let value = serde_json::from_str::<T>(&raw)?;
+            let value = serde_json::from_str::<T>(&raw)
+                .map_err(|e| ClientError::Decode(format!("invalid JSON at '{key}': {e}")))?;
             Ok(Some(value))
         } else {
             Ok(None)
@@ -186,45 +552,40 @@ impl ConsulXClient {
     ///     app/config/db      → Some JSON
     ///     app/config/cache   → Some JSON
     ///
+    /// All values come back in a single `?recurse` request. The first entry
+    /// that fails to deserialize aborts the whole call; see
+    /// [`kv_list_json_lenient`](Self::kv_list_json_lenient) to skip those instead.
     pub async fn kv_list_json<T>(&self, prefix: &str) -> Result<Vec<(String, T)>>
     where
         T: DeserializeOwned,
     {
-        let keys = self.kv_list(prefix).await?;
-
-        let mut out = Vec::new();
-
-        for key in keys {
-            if let Some(raw) = self.kv_get_raw(&key).await? {
-                if raw.trim().is_empty() {
-                    continue;
-                }
-
-                let parsed = serde_json::from_str::<T>(&raw)?;
-                out.push((key, parsed));
-            }
-        }
+        let pairs = self.kv_get_recurse(prefix).await?;
+        let listing = decode_json_pairs(pairs, false)?;
+        Ok(listing.entries)
+    }
 
-        Ok(out)
+    /// Like [`kv_list_json`](Self::kv_list_json), but entries that fail to
+    /// deserialize are collected in [`JsonListing::failures`] rather than
+    /// aborting the load.
+    pub async fn kv_list_json_lenient<T>(&self, prefix: &str) -> Result<JsonListing<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let pairs = self.kv_get_recurse(prefix).await?;
+        decode_json_pairs(pairs, true)
     }
 
     /// WATCH a single key using blocking queries + x-consul-index
     pub async fn kv_watch(&self, key: &str, index: Option<u64>) -> Result<(u64, Option<String>)> {
-        let mut params = vec![("raw", "true".into()), ("wait", "10s".into())];
+        let mut params = vec![("raw", "true".into()), ("wait", format!("{}s", WATCH_WAIT.as_secs()))];
         if let Some(i) = index {
             params.push(("index", i.to_string()));
         }
         let url = self.kv_url(key, &params);
 
-        let resp = self.http.get(url).send().await?;
+        let resp = self.send_blocking(self.http.get(url)).await?;
 
-        let new_index = resp
-            .headers()
-            .get("x-consul-index")
-            .and_then(|h| h.to_str().ok())
-            .unwrap_or("0")
-            .parse::<u64>()
-            .unwrap_or(0);
+        let new_index = consul_index(&resp);
 
         if resp.status().is_success() {
             let val = resp.text().await?;
@@ -235,7 +596,31 @@ impl ConsulXClient {
             return Ok((new_index, None));
         }
 
-        Err(anyhow::anyhow!("WATCH request failed: {}", resp.status()))
+        Err(error_from(resp).await)
+    }
+
+    /// WATCH a single key's full entry (indexes, flags, session) using
+    /// blocking queries — lets callers notice lock holder changes.
+    pub async fn kv_watch_entry(&self, key: &str, index: Option<u64>) -> Result<(u64, Option<KvPair>)> {
+        let mut params = vec![("wait", format!("{}s", WATCH_WAIT.as_secs()))];
+        if let Some(i) = index {
+            params.push(("index", i.to_string()));
+        }
+        let url = self.kv_url(key, &params);
+
+        let resp = self.send_blocking(self.http.get(url)).await?;
+        let new_index = consul_index(&resp);
+
+        if resp.status().is_success() {
+            let pairs = resp.json::<Vec<KvPair>>().await?;
+            return Ok((new_index, pairs.into_iter().next()));
+        }
+
+        if resp.status().as_u16() == 404 {
+            return Ok((new_index, None));
+        }
+
+        Err(error_from(resp).await)
     }
 
     /// WATCH prefix keys using blocking queries + ?keys
@@ -244,28 +629,87 @@ impl ConsulXClient {
         prefix: &str,
         index: Option<u64>,
     ) -> Result<(u64, Vec<String>)> {
-        let mut params = vec![("keys", String::new()), ("wait", "10s".into())];
+        let mut params = vec![("keys", String::new()), ("wait", format!("{}s", WATCH_WAIT.as_secs()))];
         if let Some(i) = index {
             params.push(("index", i.to_string()));
         }
         let url = self.kv_url(prefix, &params);
 
-        let resp = self.http.get(url).send().await?;
+        let resp = self.send_blocking(self.http.get(url)).await?;
 
-        let new_index = resp
-            .headers()
-            .get("x-consul-index")
-            .and_then(|h| h.to_str().ok())
-            .unwrap_or("0")
-            .parse::<u64>()
-            .unwrap_or(0);
+        let new_index = consul_index(&resp);
 
         if resp.status().is_success() {
-            let keys = resp.json::<Vec<String>>().await.unwrap_or_default();
+            let keys = resp.json::<Vec<String>>().await?;
             return Ok((new_index, keys));
         }
 
-        Ok((new_index, vec![]))
+        // An empty prefix 404s like kv_list; anything else is a real error.
+        if resp.status().as_u16() == 404 {
+            return Ok((new_index, vec![]));
+        }
+
+        Err(error_from(resp).await)
+    }
+}
+
+/// A stand-in agent on TCP for tests that drive real requests.
+#[cfg(test)]
+pub(crate) mod test_agent {
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    pub(crate) struct TestAgent {
+        pub addr: String,
+        seen: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl TestAgent {
+        /// `METHOD /path?query` of every request so far.
+        pub fn requests(&self) -> Vec<String> {
+            self.seen.lock().unwrap().clone()
+        }
+    }
+
+    /// Answer with `responses` (status, body) in turn, repeating the last,
+    /// and close each connection.
+    pub(crate) async fn spawn(responses: Vec<(u16, &'static str)>) -> TestAgent {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        tokio::spawn(async move {
+            for n in 0.. {
+                let (mut conn, _) = listener.accept().await.unwrap();
+                // read the head and any body so closing doesn't reset the connection
+                let mut buf = Vec::new();
+                let mut chunk = [0; 4096];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = conn.read(&mut chunk).await.unwrap();
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let text = String::from_utf8_lossy(&buf).into_owned();
+                let (head, body) = text.split_once("\r\n\r\n").unwrap();
+                let length: usize = head
+                    .lines()
+                    .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                    .unwrap_or(0);
+                let mut remaining = length.saturating_sub(body.len());
+                while remaining > 0 {
+                    remaining -= conn.read(&mut chunk).await.unwrap();
+                }
+
+                let line = head.lines().next().unwrap_or_default();
+                log.lock().unwrap().push(line.trim_end_matches(" HTTP/1.1").to_string());
+                let (status, body) = responses[n.min(responses.len() - 1)];
+                let resp = format!(
+                    "HTTP/1.1 {status} X\r\nconnection: close\r\ncontent-length: {}\r\n\r\n{body}",
+                    body.len()
+                );
+                conn.write_all(resp.as_bytes()).await.unwrap();
+            }
+        });
+        TestAgent { addr, seen }
     }
 }
 
@@ -332,4 +776,187 @@ mod tests {
         let c = client(None);
         assert_eq!(c.kv_url("k", &[]), "http://127.0.0.1:8500/v1/kv/k");
     }
+
+    #[tokio::test]
+    async fn delete_tree_refuses_empty_prefix() {
+        let c = client(None);
+        for prefix in ["", "/", "//"] {
+            let err = c.kv_delete_tree(prefix).await.unwrap_err();
+            assert!(err.to_string().contains("empty prefix"), "{prefix:?}: {err}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket_carries_kv_requests() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::UnixListener;
+
+        let dir = tempfile::tempdir().unwrap();
+        let sock = dir.path().join("consul.sock");
+        let listener = UnixListener::bind(&sock).unwrap();
+
+        // stand-in agent: answer one request and hand back what it saw
+        let server = tokio::spawn(async move {
+            let (mut conn, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = conn.read(&mut buf).await.unwrap();
+            let body = "db.internal";
+            let resp = format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{body}", body.len());
+            conn.write_all(resp.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        });
+
+        let c = ConsulXClient::with_options(&format!("unix://{}", sock.display()), None, Some("dc1".into())).unwrap();
+        assert_eq!(c.kv_get_raw("app/db host").await.unwrap().as_deref(), Some("db.internal"));
+
+        let request = server.await.unwrap();
+        let request_line = request.lines().next().unwrap();
+        assert_eq!(request_line, "GET /v1/kv/app/db%20host?raw=true&dc=dc1 HTTP/1.1");
+    }
+
+    fn fast_retries(addr: &str) -> ConsulXClient {
+        let retry = RetryPolicy { base_delay: Duration::ZERO, ..RetryPolicy::default() };
+        ConsulXClientBuilder::new(addr).retry_policy(retry).build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_retry_a_server_error() {
+        let agent = test_agent::spawn(vec![(503, ""), (200, "db.internal")]).await;
+        let c = fast_retries(&agent.addr);
+        assert_eq!(c.kv_get_raw("app/db").await.unwrap().as_deref(), Some("db.internal"));
+        assert_eq!(agent.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn writes_are_not_replayed_after_a_server_error() {
+        let agent = test_agent::spawn(vec![(503, "")]).await;
+        let c = fast_retries(&agent.addr);
+        let err = c.kv_put("app/db", "x").await.unwrap_err();
+        assert!(matches!(err, ClientError::ServerError { status: 503, .. }), "{err}");
+        let err = c.kv_put_cas("app/db", "x", 7).await.unwrap_err();
+        assert!(matches!(err, ClientError::ServerError { status: 503, .. }), "{err}");
+        assert_eq!(agent.requests(), ["PUT /v1/kv/app/db", "PUT /v1/kv/app/db?cas=7"]);
+    }
+
+    #[tokio::test]
+    async fn failover_counts_against_the_attempt_budget() {
+        let down = test_agent::spawn(vec![(503, "")]).await;
+        let up = test_agent::spawn(vec![(200, "db.internal")]).await;
+
+        let c = ConsulXClientBuilder::new(&format!("{},{}", down.addr, up.addr))
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        assert!(c.kv_get_raw("app/db").await.is_err());
+        assert_eq!((down.requests().len(), up.requests().len()), (1, 0));
+
+        let c = fast_retries(&format!("{},{}", down.addr, up.addr));
+        assert_eq!(c.kv_get_raw("app/db").await.unwrap().as_deref(), Some("db.internal"));
+        assert_eq!((down.requests().len(), up.requests().len()), (2, 1));
+        assert_eq!(c.current_endpoint(), 1);
+    }
+
+    #[test]
+    fn kv_url_appends_cas_index() {
+        let c = client(None);
+        assert_eq!(
+            c.kv_url("k", &[("cas", 42.to_string())]),
+            "http://127.0.0.1:8500/v1/kv/k?cas=42"
+        );
+    }
+
+    #[test]
+    fn cas_outcome_from_body() {
+        assert_eq!(CasOutcome::from_body("true"), CasOutcome::Applied);
+        assert_eq!(CasOutcome::from_body("true\n"), CasOutcome::Applied);
+        assert_eq!(CasOutcome::from_body("false"), CasOutcome::Conflict);
+        assert_eq!(CasOutcome::from_body(""), CasOutcome::Conflict);
+    }
+
+    #[test]
+    fn kv_pair_decodes_consul_response() {
+        let body = r#"[{
+            "LockIndex": 1,
+            "Key": "app/db",
+            "Flags": 42,
+            "Value": "aGVsbG8=",
+            "Session": "adf4238a-882b-9ddc-4a9d-5b6758e4159e",
+            "CreateIndex": 100,
+            "ModifyIndex": 200
+        }]"#;
+        let pairs: Vec<KvPair> = serde_json::from_str(body).unwrap();
+        let p = &pairs[0];
+        assert_eq!(p.key, "app/db");
+        assert_eq!(p.value_str().as_deref(), Some("hello"));
+        assert_eq!((p.create_index, p.modify_index, p.lock_index), (100, 200, 1));
+        assert_eq!(p.flags, 42);
+        assert_eq!(p.session.as_deref(), Some("adf4238a-882b-9ddc-4a9d-5b6758e4159e"));
+    }
+
+    #[test]
+    fn kv_pair_null_value_and_missing_session() {
+        let body = r#"{"LockIndex":0,"Key":"dir/","Flags":0,"Value":null,"CreateIndex":1,"ModifyIndex":1}"#;
+        let p: KvPair = serde_json::from_str(body).unwrap();
+        assert_eq!(p.value, None);
+        assert_eq!(p.session, None);
+    }
+
+    fn pair(key: &str, value: Option<&str>) -> KvPair {
+        KvPair {
+            key: key.into(),
+            value: value.map(|v| v.as_bytes().to_vec()),
+            create_index: 1,
+            modify_index: 1,
+            lock_index: 0,
+            flags: 0,
+            session: None,
+        }
+    }
+
+    #[test]
+    fn decode_json_pairs_skips_folders_and_blanks() {
+        let pairs = vec![
+            pair("app/", None),
+            pair("app/a", Some(r#"{"n":1}"#)),
+            pair("app/b", Some("  ")),
+        ];
+        let listing = decode_json_pairs::<serde_json::Value>(pairs, false).unwrap();
+        assert_eq!(listing.entries.len(), 1);
+        assert_eq!(listing.entries[0].0, "app/a");
+        assert!(listing.failures.is_empty());
+    }
+
+    #[test]
+    fn decode_json_pairs_strict_aborts_on_bad_entry() {
+        let pairs = vec![pair("app/a", Some("1")), pair("app/b", Some("not json"))];
+        let err = decode_json_pairs::<u32>(pairs, false).unwrap_err();
+        assert!(err.to_string().contains("app/b"));
+    }
+
+    #[test]
+    fn decode_json_pairs_lenient_reports_failures() {
+        let pairs = vec![
+            pair("app/a", Some("1")),
+            pair("app/b", Some("not json")),
+            pair("app/c", Some("3")),
+        ];
+        let listing = decode_json_pairs::<u32>(pairs, true).unwrap();
+        assert_eq!(listing.entries, vec![("app/a".into(), 1), ("app/c".into(), 3)]);
+        assert_eq!(listing.failures.len(), 1);
+        assert_eq!(listing.failures[0].0, "app/b");
+    }
+
+    #[test]
+    fn api_url_shares_query_handling() {
+        let c = client(Some("dc1"));
+        assert_eq!(c.api_url("v1/txn", &[]), "http://127.0.0.1:8500/v1/txn?dc=dc1");
+    }
+
+    #[test]
+    fn with_dc_only_changes_datacenter() {
+        let c = client(Some("dc1")).with_dc(Some("dc2".into()));
+        assert_eq!(c.kv_url("k", &[]), "http://127.0.0.1:8500/v1/kv/k?dc=dc2");
+        assert_eq!(client(Some("dc1")).with_dc(None).kv_url("k", &[]), "http://127.0.0.1:8500/v1/kv/k");
+    }
 }
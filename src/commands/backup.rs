@@ -0,0 +1,91 @@
+use crate::backup::Backup;
+use crate::client::ConsulXClient;
+use crate::commands::confirm;
+use crate::errors::{ConsulXError, Result};
+use colored::Colorize;
+
+pub async fn cmd_backup(client: &ConsulXClient, prefix: &str, file: &str) -> Result<()> {
+    let backup = client.kv_backup(prefix).await?;
+    backup.write_to(file)?;
+    println!("OK ({} keys backed up to {file})", backup.entries.len());
+    Ok(())
+}
+
+/// Verify an archive, show what restoring it would change, and apply it
+/// after confirmation (or straight away with `--yes`).
+pub async fn cmd_restore(client: &ConsulXClient, file: &str, yes: bool) -> Result<()> {
+    let backup = Backup::read_from(file)?;
+    println!(
+        "Backup of '{}' from {}{} taken at {} ({} keys, checksum OK)",
+        backup.prefix,
+        backup.source.addr,
+        backup.source.dc.as_deref().map(|dc| format!(" [dc={dc}]")).unwrap_or_default(),
+        backup.created_at,
+        backup.entries.len()
+    );
+
+    let plan = client.kv_restore_plan(&backup).await?;
+    for key in &plan.create {
+        println!("  {} {key}", "+".green());
+    }
+    for key in &plan.overwrite {
+        println!("  {} {key}", "~".yellow());
+    }
+    println!(
+        "{} to create, {} to overwrite, {} unchanged",
+        plan.create.len(),
+        plan.overwrite.len(),
+        plan.unchanged.len()
+    );
+
+    if plan.is_noop() {
+        println!("Nothing to restore");
+        return Ok(());
+    }
+    if !yes && !confirm("Restore these keys?")? {
+        println!("Aborted");
+        return Ok(());
+    }
+
+    let report = client.kv_restore(&backup, &plan).await?;
+    if let Some((offset, errors)) = report.rolled_back {
+        let mut errors: Vec<String> = errors
+            .iter()
+            .map(|err| format!("op #{}: {}", offset + err.op_index, err.what))
+            .collect();
+        errors.push(format!("{} keys written before this chunk; restore is incomplete", report.imported));
+        return Err(ConsulXError::TxnRolledBack { errors });
+    }
+    println!("OK ({} keys restored)", report.imported);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::{BackupEntry, BackupSource};
+    use crate::client::test_agent;
+    use crate::export::ExportEntry;
+
+    #[tokio::test]
+    async fn rolled_back_restore_is_a_txn_error() {
+        let body = r#"{"Results":null,"Errors":[{"OpIndex":0,"What":"permission denied"}]}"#;
+        let agent = test_agent::spawn(vec![(200, "[]"), (409, body)]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+
+        let entry = BackupEntry {
+            entry: ExportEntry { key: "app/a".into(), flags: 0, value: b"1".to_vec() },
+            create_index: 1,
+            modify_index: 1,
+            lock_index: 0,
+        };
+        let source = BackupSource { addr: agent.addr.clone(), dc: None };
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("app.cxb");
+        Backup::new(source, "app/", vec![entry]).unwrap().write_to(&file).unwrap();
+
+        let err = cmd_restore(&client, file.to_str().unwrap(), true).await.unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert!(err.to_string().contains("0 keys written before this chunk"), "{err}");
+    }
+}
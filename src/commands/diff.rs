@@ -0,0 +1,197 @@
+use crate::client::ConsulXClient;
+use crate::commands::sync::{Tree, folder_prefix, read_remote};
+use crate::errors::Result;
+use crate::export::ExportEntry;
+use crate::parser::DiffTarget;
+use colored::Colorize;
+use serde_json::Value;
+use similar::TextDiff;
+use std::fs;
+use std::path::Path;
+
+/// Keys only on the left, only on the right, and present on both with
+/// different values. All keys are relative to the compared prefix.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct TreeDiff {
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+pub(crate) fn diff_trees(left: &Tree, right: &Tree) -> TreeDiff {
+    let mut diff = TreeDiff::default();
+    for (key, value) in left {
+        match right.get(key) {
+            None => diff.removed.push(key.clone()),
+            Some(other) if !values_equal(value, other) => diff.changed.push(key.clone()),
+            Some(_) => {}
+        }
+    }
+    diff.added = right.keys().filter(|k| !left.contains_key(*k)).cloned().collect();
+    diff
+}
+
+/// Values that both parse as JSON are compared as documents, so key order
+/// and whitespace don't count as changes; anything else is compared bytewise.
+fn values_equal(a: &[u8], b: &[u8]) -> bool {
+    match (serde_json::from_slice::<Value>(a), serde_json::from_slice::<Value>(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Text used for the line diff: JSON is pretty-printed with sorted keys so
+/// that structurally equal parts line up.
+fn render(value: &[u8]) -> String {
+    match serde_json::from_slice::<Value>(value) {
+        Ok(json) if !value.trim_ascii().is_empty() => {
+            serde_json::to_string_pretty(&json).unwrap_or_default() + "\n"
+        }
+        _ => {
+            let mut text = String::from_utf8_lossy(value).into_owned();
+            if !text.ends_with('\n') {
+                text.push('\n');
+            }
+            text
+        }
+    }
+}
+
+/// Keys under `prefix` from a `consul kv export` file, relative to it.
+fn read_export(path: &Path, prefix: &str) -> Result<Tree> {
+    let entries: Vec<ExportEntry> = serde_json::from_str(&fs::read_to_string(path)?)?;
+    Ok(entries
+        .into_iter()
+        .filter_map(|e| {
+            let rel = e.key.strip_prefix(prefix)?.to_string();
+            (!rel.is_empty() && !rel.ends_with('/')).then_some((rel, e.value))
+        })
+        .collect())
+}
+
+/// Both sides of a diff with their display names. Prefixes are read as
+/// folders, so `app/v1` doesn't also pull in `app/v10/*`.
+async fn read_sides(client: &ConsulXClient, target: &DiffTarget) -> Result<(String, String, Tree, Tree)> {
+    Ok(match target {
+        DiffTarget::Datacenters { prefix, a, b } => {
+            let prefix = folder_prefix(prefix);
+            let left = read_remote(&client.with_dc(Some(a.clone())), &prefix).await?;
+            let right = read_remote(&client.with_dc(Some(b.clone())), &prefix).await?;
+            (format!("{a}:{prefix}"), format!("{b}:{prefix}"), left, right)
+        }
+        DiffTarget::Prefixes { a, b } if Path::new(b).is_file() => {
+            let a = folder_prefix(a);
+            let left = read_remote(client, &a).await?;
+            let right = read_export(Path::new(b), &a)?;
+            (a, b.clone(), left, right)
+        }
+        DiffTarget::Prefixes { a, b } => {
+            let (a, b) = (folder_prefix(a), folder_prefix(b));
+            let left = read_remote(client, &a).await?;
+            let right = read_remote(client, &b).await?;
+            (a, b, left, right)
+        }
+    })
+}
+
+pub async fn cmd_diff(client: &ConsulXClient, target: &DiffTarget) -> Result<()> {
+    let (left_name, right_name, left, right) = read_sides(client, target).await?;
+
+    let diff = diff_trees(&left, &right);
+    for key in &diff.removed {
+        println!("{}", format!("- {key}").red());
+    }
+    for key in &diff.added {
+        println!("{}", format!("+ {key}").green());
+    }
+    for key in &diff.changed {
+        println!("{}", format!("~ {key}").yellow());
+        let (old, new) = (render(&left[key]), render(&right[key]));
+        let text = TextDiff::from_lines(&old, &new);
+        let unified = text
+            .unified_diff()
+            .header(&format!("{left_name}{key}"), &format!("{right_name}{key}"))
+            .to_string();
+        for line in unified.lines() {
+            let colored = match line.chars().next() {
+                Some('+') if !line.starts_with("+++") => line.green(),
+                Some('-') if !line.starts_with("---") => line.red(),
+                Some('@') => line.cyan(),
+                _ => line.normal(),
+            };
+            println!("    {colored}");
+        }
+    }
+
+    let unchanged = left.len() - diff.removed.len() - diff.changed.len();
+    println!(
+        "{} removed, {} added, {} changed, {} unchanged",
+        diff.removed.len(),
+        diff.added.len(),
+        diff.changed.len(),
+        unchanged
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_agent;
+
+    fn tree(items: &[(&str, &str)]) -> Tree {
+        items
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn json_values_compare_structurally() {
+        assert!(values_equal(br#"{"a":1,"b":[1,2]}"#, br#"{ "b": [1, 2], "a": 1 }"#));
+        assert!(!values_equal(br#"{"a":1}"#, br#"{"a":2}"#));
+        // non-JSON falls back to exact comparison
+        assert!(!values_equal(b"host=a", b"host=a "));
+    }
+
+    #[test]
+    fn diff_trees_classifies_keys() {
+        let left = tree(&[("a", "1"), ("b", r#"{"x":1,"y":2}"#), ("c", "old")]);
+        let right = tree(&[("b", r#"{"y":2,"x":1}"#), ("c", "new"), ("d", "4")]);
+        assert_eq!(
+            diff_trees(&left, &right),
+            TreeDiff {
+                removed: vec!["a".into()],
+                added: vec!["d".into()],
+                changed: vec!["c".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn render_pretty_prints_json_with_sorted_keys() {
+        assert_eq!(render(br#"{"b":1,"a":2}"#), "{\n  \"a\": 2,\n  \"b\": 1\n}\n");
+        assert_eq!(render(b"plain"), "plain\n");
+    }
+
+    #[tokio::test]
+    async fn sibling_prefixes_are_not_mixed_in() {
+        let v1 = r#"[{"Key":"app/v1/x","Value":"MQ==","Flags":0,"CreateIndex":1,"ModifyIndex":1,"LockIndex":0}]"#;
+        let agent = test_agent::spawn(vec![(200, v1)]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+        let target = DiffTarget::Prefixes { a: "app/v1".into(), b: "app/v2".into() };
+
+        let (a, b, left, _) = read_sides(&client, &target).await.unwrap();
+        assert_eq!((a.as_str(), b.as_str()), ("app/v1/", "app/v2/"));
+        assert_eq!(left.keys().collect::<Vec<_>>(), ["x"]);
+        assert_eq!(agent.requests(), ["GET /v1/kv/app/v1/?recurse", "GET /v1/kv/app/v2/?recurse"]);
+
+        // an export file holding both siblings only contributes the folder's keys
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("export.json");
+        fs::write(&file, r#"[{"key":"app/v1/x","value":"MQ=="},{"key":"app/v10/y","value":"Mg=="}]"#).unwrap();
+        let target = DiffTarget::Prefixes { a: "app/v1".into(), b: file.to_str().unwrap().into() };
+        let (_, _, _, right) = read_sides(&client, &target).await.unwrap();
+        assert_eq!(right.keys().collect::<Vec<_>>(), ["x"]);
+    }
+}
@@ -0,0 +1,245 @@
+use crate::client::{ConsulXClient, KvPair};
+use crate::commands::{ask, confirm};
+use crate::commands::kv::parse_stored_json;
+use crate::errors::{ConsulXError, Result};
+use crate::format::Format;
+use crate::schema::SchemaRegistry;
+use crate::txn::{KvOp, Txn, TxnOutcome};
+use colored::Colorize;
+use serde_json::Value;
+use similar::TextDiff;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command as ProcCommand;
+
+const CONFLICT_START: &str = "<<<<<<< yours";
+const CONFLICT_MID: &str = "=======";
+
+/// How the edited text is checked and turned back into a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Stored as typed, no validation.
+    Text,
+    /// Stored as typed, but must stay valid JSON (and match any schema).
+    Json,
+    /// Shown in another format, converted back to minified JSON.
+    Convert(Format),
+}
+
+impl Mode {
+    fn extension(self) -> &'static str {
+        match self {
+            Mode::Text => "txt",
+            Mode::Json => "json",
+            Mode::Convert(format) => format.extension(),
+        }
+    }
+
+    /// The text the editor should start with for a stored value.
+    fn render(self, key: &str, stored: &str) -> Result<String> {
+        match self {
+            Mode::Convert(format) if !stored.trim().is_empty() => {
+                Ok(format.render(&parse_stored_json(key, stored)?)?)
+            }
+            Mode::Convert(_) => Ok(String::new()),
+            Mode::Text | Mode::Json => Ok(stored.to_string()),
+        }
+    }
+
+    /// The value to store for edited `text`, or why it can't be stored.
+    fn encode(self, key: &str, text: &str, schemas: &SchemaRegistry) -> std::result::Result<String, String> {
+        if text.lines().any(|l| l.starts_with(CONFLICT_START)) {
+            return Err("unresolved conflict markers".into());
+        }
+
+        let (value, doc) = match self {
+            Mode::Text => return Ok(text.to_string()),
+            Mode::Json => {
+                let doc: Value = serde_json::from_str(text).map_err(|e| format!("not valid JSON: {e}"))?;
+                (text.to_string(), doc)
+            }
+            Mode::Convert(format) => {
+                let doc = format.parse(text).map_err(|e| format!("not valid {format}: {e}"))?;
+                (serde_json::to_string(&doc).map_err(|e| e.to_string())?, doc)
+            }
+        };
+
+        let violations = schemas.validate(key, &doc);
+        if !violations.is_empty() {
+            let lines: Vec<String> = violations.iter().map(|v| format!("  {v}")).collect();
+            return Err(format!("schema violation:\n{}", lines.join("\n")));
+        }
+        Ok(value)
+    }
+}
+
+/// Replace the whole file with both sides of a conflict for hand-merging.
+fn conflict_text(yours: &str, theirs: &str, index: u64) -> String {
+    let mut out = format!("{CONFLICT_START}\n{yours}");
+    if !yours.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&format!("{CONFLICT_MID}\n{theirs}"));
+    if !theirs.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(&format!(">>>>>>> consul (index {index})\n"));
+    out
+}
+
+/// Write `text` to `path`, run `$EDITOR` on it and read it back. `None`
+/// if the editor failed.
+fn run_editor(path: &Path, text: &str) -> Result<Option<String>> {
+    fs::write(path, text)?;
+
+    // pick editor; support values with args, e.g. EDITOR="code -w"
+    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "nano".to_string());
+    let mut editor_parts = editor.split_whitespace();
+    let program = editor_parts.next().unwrap_or("nano");
+
+    // launch editor (blocking)
+    let status = ProcCommand::new(program)
+        .args(editor_parts)
+        .arg(path)
+        .status()?;
+
+    if !status.success() {
+        return Ok(None);
+    }
+    Ok(Some(fs::read_to_string(path)?))
+}
+
+/// CAS write that keeps the key's flags; index 0 means "only if absent".
+async fn write_cas(client: &ConsulXClient, key: &str, value: &str, flags: u64, index: u64) -> Result<bool> {
+    let mut txn = Txn::new();
+    txn.push(KvOp::Cas {
+        key: key.to_string(),
+        value: value.as_bytes().to_vec(),
+        flags,
+        index,
+    });
+    Ok(matches!(client.txn_commit(&txn).await?, TxnOutcome::Committed(_)))
+}
+
+fn stored_text(key: &str, entry: Option<&KvPair>) -> Result<String> {
+    let bytes = entry.and_then(|e| e.value.clone()).unwrap_or_default();
+    String::from_utf8(bytes)
+        .map_err(|_| ConsulXError::InvalidArgument(format!("value at '{key}' is binary and can't be edited")))
+}
+
+/// Edit key using $EDITOR, write back on save.
+///
+/// The value goes into a private temp file named for its format. Saving
+/// writes back with CAS against the index that was read, so a concurrent
+/// change is never silently overwritten; content that fails JSON or schema
+/// validation can be fixed in the editor instead of being lost.
+pub async fn cmd_edit(client: &ConsulXClient, key: &str, as_format: Option<Format>) -> Result<()> {
+    let schemas = SchemaRegistry::load(client).await?;
+    let mut entry = client.kv_get_entry(key).await?;
+    let stored = stored_text(key, entry.as_ref())?;
+
+    let is_json = !stored.trim().is_empty() && serde_json::from_str::<Value>(&stored).is_ok();
+    let mode = match as_format {
+        Some(format) => Mode::Convert(format),
+        None if is_json || schemas.applies_to(key) => Mode::Json,
+        None => Mode::Text,
+    };
+
+    // 0600 and uniquely named, removed when dropped
+    let file = tempfile::Builder::new()
+        .prefix("consulx-")
+        .suffix(&format!(".{}", mode.extension()))
+        .tempfile()?;
+
+    let mut base = mode.render(key, &stored)?;
+    let mut text = base.clone();
+    loop {
+        let Some(edited) = run_editor(file.path(), &text)? else {
+            eprintln!("Editor exited with non-zero status, aborting update");
+            return Ok(());
+        };
+        text = edited;
+        if text == base {
+            println!("No changes");
+            return Ok(());
+        }
+
+        let value = match mode.encode(key, &text, &schemas) {
+            Ok(value) => value,
+            Err(why) => {
+                eprintln!("{} {why}", "Invalid:".red());
+                if confirm("Re-open the editor to fix it?")? {
+                    continue;
+                }
+                println!("Aborted");
+                return Ok(());
+            }
+        };
+
+        let (flags, index) = entry.as_ref().map_or((0, 0), |e| (e.flags, e.modify_index));
+        if write_cas(client, key, &value, flags, index).await? {
+            println!("OK (edited)");
+            return Ok(());
+        }
+
+        // Someone else wrote the key since we read it.
+        entry = client.kv_get_entry(key).await?;
+        let theirs = mode.render(key, &stored_text(key, entry.as_ref())?)?;
+        let new_index = entry.as_ref().map_or(0, |e| e.modify_index);
+        eprintln!(
+            "{} '{key}' changed while you were editing (index {index} -> {new_index}). Their changes:",
+            "Conflict:".yellow()
+        );
+        let diff = TextDiff::from_lines(&base, &theirs);
+        eprint!("{}", diff.unified_diff().header("read", "now"));
+        std::io::stderr().flush()?;
+
+        match ask("[r]e-open your version, [m]erge both in the editor, or [a]bort?")?.trim() {
+            "r" | "R" => {}
+            "m" | "M" => text = conflict_text(&text, &theirs, new_index),
+            _ => {
+                println!("Aborted");
+                return Ok(());
+            }
+        }
+        base = theirs;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_mode_requires_valid_json() {
+        let schemas = SchemaRegistry::default();
+        assert_eq!(Mode::Json.encode("k", "{\"a\": 1}\n", &schemas), Ok("{\"a\": 1}\n".into()));
+        assert!(Mode::Json.encode("k", "{\"a\": ", &schemas).unwrap_err().contains("not valid JSON"));
+        assert_eq!(Mode::Text.encode("k", "anything", &schemas), Ok("anything".into()));
+    }
+
+    #[test]
+    fn convert_mode_stores_minified_json() {
+        let schemas = SchemaRegistry::default();
+        let mode = Mode::Convert(Format::Yaml);
+        assert_eq!(mode.encode("k", "a: 1\nb: [x]\n", &schemas), Ok(r#"{"a":1,"b":["x"]}"#.into()));
+        assert_eq!(mode.render("k", r#"{"a":1}"#).unwrap(), "a: 1\n");
+        assert_eq!(mode.render("k", "").unwrap(), "");
+    }
+
+    #[test]
+    fn leftover_conflict_markers_are_rejected() {
+        let text = conflict_text("mine", "theirs", 9);
+        assert_eq!(text, "<<<<<<< yours\nmine\n=======\ntheirs\n>>>>>>> consul (index 9)\n");
+        let err = Mode::Text.encode("k", &text, &SchemaRegistry::default()).unwrap_err();
+        assert!(err.contains("conflict markers"));
+    }
+
+    #[test]
+    fn schema_violations_fail_encoding() {
+        let schemas = SchemaRegistry::from_config(&serde_json::json!({"k": {"type": "object"}}), None).unwrap();
+        let err = Mode::Json.encode("k", "[1]", &schemas).unwrap_err();
+        assert!(err.starts_with("schema violation:"), "{err}");
+    }
+}
@@ -0,0 +1,92 @@
+use crate::client::ConsulXClient;
+use crate::errors::{ConsulXError, Result};
+use crate::export::{ExportEntry, rewrite_prefix};
+use crate::schema::SchemaRegistry;
+use colored::Colorize;
+use std::fs;
+
+/// Dump `prefix` as `consul kv export` JSON to `file`, or stdout.
+pub async fn cmd_export(client: &ConsulXClient, prefix: &str, file: Option<&str>) -> Result<()> {
+    let entries = client.kv_export(prefix).await?;
+    let json = serde_json::to_string_pretty(&entries)?;
+
+    match file {
+        Some(path) => {
+            fs::write(path, json + "\n")?;
+            println!("OK ({} keys exported to {path})", entries.len());
+        }
+        None => println!("{json}"),
+    }
+    Ok(())
+}
+
+/// Load an export file, optionally rewriting a key prefix, and write it
+/// back in transaction-sized chunks.
+pub async fn cmd_import(
+    client: &ConsulXClient,
+    file: &str,
+    rewrite: Option<&(String, String)>,
+) -> Result<()> {
+    let data = fs::read_to_string(file)?;
+    let mut entries: Vec<ExportEntry> = serde_json::from_str(&data)?;
+    if let Some((old, new)) = rewrite {
+        rewrite_prefix(&mut entries, old, new);
+    }
+
+    // Validate everything up front so a bad entry can't leave a partial import.
+    let schemas = SchemaRegistry::load(client).await?;
+    let mut invalid = 0;
+    for e in &entries {
+        for v in schemas.validate_raw(&e.key, &e.value) {
+            eprintln!("  [{}] {v}", e.key);
+            invalid += 1;
+        }
+    }
+    if invalid > 0 {
+        return Err(ConsulXError::InvalidArgument(format!(
+            "{invalid} schema violations in {file}; nothing imported"
+        )));
+    }
+
+    let report = client.kv_import(&entries).await?;
+    match report.rolled_back {
+        None => println!("OK ({} keys imported)", report.imported),
+        Some((offset, errors)) => {
+            eprintln!(
+                "{} {} of {} keys imported; chunk starting at entry {offset} rolled back",
+                "Import incomplete:".red(),
+                report.imported,
+                entries.len()
+            );
+            let errors = errors
+                .into_iter()
+                .map(|err| {
+                    let key = entries.get(offset + err.op_index).map(|e| e.key.as_str()).unwrap_or("?");
+                    format!("[{key}]: {}", err.what)
+                })
+                .collect();
+            return Err(ConsulXError::TxnRolledBack { errors });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_agent;
+
+    #[tokio::test]
+    async fn rolled_back_import_is_a_txn_error() {
+        let body = r#"{"Results":null,"Errors":[{"OpIndex":1,"What":"permission denied"}]}"#;
+        let agent = test_agent::spawn(vec![(404, ""), (409, body)]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("export.json");
+        fs::write(&file, r#"[{"key":"app/a","flags":0,"value":""},{"key":"app/b","flags":0,"value":""}]"#).unwrap();
+
+        let err = cmd_import(&client, file.to_str().unwrap(), None).await.unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert!(err.to_string().contains("[app/b]: permission denied"), "{err}");
+    }
+}
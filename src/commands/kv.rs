@@ -1,31 +1,115 @@
-use crate::client::ConsulXClient;
-use crate::errors::Result;
+use crate::client::{CasOutcome, ConsulXClient};
+use crate::commands::confirm_typed;
+use crate::errors::{ConsulXError, Result};
+use crate::format::Format;
+use crate::jsonpath::JsonPath;
+use crate::schema::SchemaRegistry;
+use crate::update::DEFAULT_CAS_RETRIES;
 use serde_json::Value;
 use std::collections::BTreeMap;
-use std::fs;
-use std::process::Command as ProcCommand;
-use std::time::{SystemTime, UNIX_EPOCH};
 
-pub async fn cmd_get(client: &ConsulXClient, key: &str) -> Result<()> {
-    match client.kv_get_raw(key).await? {
-        Some(v) => println!("{v}"),
-        None => println!("<nil>"),
+/// Print a key's value, or with `as_format` its JSON value converted.
+pub async fn cmd_get(client: &ConsulXClient, key: &str, as_format: Option<Format>) -> Result<()> {
+    let v = client
+        .kv_get_raw(key)
+        .await?
+        .ok_or_else(|| ConsulXError::NotFound(key.to_string()))?;
+
+    match as_format {
+        None => println!("{v}"),
+        Some(format) => print!("{}", format.render(&parse_stored_json(key, &v)?)?),
     }
     Ok(())
 }
 
-pub async fn cmd_put(client: &ConsulXClient, key: &str, value: &str) -> Result<()> {
-    client.kv_put(key, value).await?;
+pub(crate) fn parse_stored_json(key: &str, raw: &str) -> Result<Value> {
+    serde_json::from_str(raw).map_err(|e| {
+        ConsulXError::InvalidArgument(format!("value at '{key}' is not JSON, so it can't be converted: {e}"))
+    })
+}
+
+/// What `put` stores: `value` unchanged, or with `from`, validated in that
+/// format and then kept verbatim or converted to minified JSON.
+pub(crate) fn encode_put_value(value: &str, from: Option<Format>, verbatim: bool) -> Result<String> {
+    let Some(format) = from else {
+        return Ok(value.to_string());
+    };
+    let doc = format
+        .parse(value)
+        .map_err(|e| ConsulXError::InvalidArgument(format!("value is not valid {format}: {e}")))?;
+    if verbatim {
+        Ok(value.to_string())
+    } else {
+        Ok(serde_json::to_string(&doc)?)
+    }
+}
+
+/// Print a key's value along with its Consul metadata.
+pub async fn cmd_stat(client: &ConsulXClient, key: &str) -> Result<()> {
+    let Some(pair) = client.kv_get_entry(key).await? else {
+        return Err(ConsulXError::NotFound(key.to_string()));
+    };
+
+    println!("Key:         {}", pair.key);
+    println!("CreateIndex: {}", pair.create_index);
+    println!("ModifyIndex: {}", pair.modify_index);
+    println!("LockIndex:   {}", pair.lock_index);
+    println!("Flags:       {}", pair.flags);
+    println!("Session:     {}", pair.session.as_deref().unwrap_or("-"));
+    println!("Value:       {}", pair.value_str().unwrap_or_default());
+    Ok(())
+}
+
+pub async fn cmd_put(client: &ConsulXClient, key: &str, value: &str, cas: Option<u64>) -> Result<()> {
+    match cas {
+        Some(index) => check_cas(key, index, client.kv_put_cas(key, value, index).await?)?,
+        None => client.kv_put(key, value).await?,
+    }
     println!("OK");
     Ok(())
 }
 
-pub async fn cmd_delete(client: &ConsulXClient, key: &str) -> Result<()> {
-    client.kv_delete(key).await?;
+pub async fn cmd_delete(client: &ConsulXClient, key: &str, cas: Option<u64>) -> Result<()> {
+    match cas {
+        Some(index) => check_cas(key, index, client.kv_delete_cas(key, index).await?)?,
+        None => client.kv_delete(key).await?,
+    }
     println!("OK");
     Ok(())
 }
 
+/// Preview every key under `prefix`, confirm by retyping the prefix unless
+/// `yes`, then delete them all in one request.
+pub async fn cmd_delete_tree(client: &ConsulXClient, prefix: &str, yes: bool) -> Result<()> {
+    let keys = client.kv_list(prefix).await?;
+    if keys.is_empty() {
+        println!("<empty>");
+        return Ok(());
+    }
+
+    println!("{} keys under '{prefix}' will be deleted:", keys.len());
+    print_tree(prefix, keys.iter().map(|k| (k.clone(), String::new())));
+    if !yes && !confirm_typed("This cannot be undone.", prefix)? {
+        println!("Aborted");
+        return Ok(());
+    }
+
+    client.kv_delete_tree(prefix).await?;
+    println!("OK ({} keys deleted)", keys.len());
+    Ok(())
+}
+
+/// Turn a lost CAS race into an error so the caller sees it as a failure.
+fn check_cas(key: &str, index: u64, outcome: CasOutcome) -> Result<()> {
+    match outcome {
+        CasOutcome::Applied => Ok(()),
+        CasOutcome::Conflict => Err(ConsulXError::CasConflict {
+            key: key.to_string(),
+            index,
+        }),
+    }
+}
+
 pub async fn cmd_list(client: &ConsulXClient, prefix: &str) -> Result<()> {
     let keys = client.kv_list(prefix).await?;
     if keys.is_empty() {
@@ -39,28 +123,103 @@ pub async fn cmd_list(client: &ConsulXClient, prefix: &str) -> Result<()> {
 }
 
 /// JSON-aware GET: parses as JSON and pretty-prints
-pub async fn cmd_get_json(client: &ConsulXClient, key: &str) -> Result<()> {
-    match client.kv_get_raw(key).await? {
-        Some(v) => {
-            match serde_json::from_str::<Value>(&v) {
-                Ok(json) => {
-                    let pretty = serde_json::to_string_pretty(&json)?;
-                    println!("{pretty}");
-                }
-                Err(_) => {
-                    eprintln!("Value is not valid JSON, raw:");
-                    println!("{v}");
-                }
-            }
+pub async fn cmd_get_json(client: &ConsulXClient, key: &str, path: Option<&str>) -> Result<()> {
+    let v = client
+        .kv_get_raw(key)
+        .await?
+        .ok_or_else(|| ConsulXError::NotFound(key.to_string()))?;
+
+    let json = match (serde_json::from_str::<Value>(&v), path) {
+        (Ok(json), _) => json,
+        (Err(_), None) => {
+            eprintln!("Value is not valid JSON, raw:");
+            println!("{v}");
+            return Ok(());
         }
-        None => println!("<nil>"),
+        (Err(e), Some(_)) => {
+            return Err(ConsulXError::InvalidArgument(format!("value at '{key}' is not valid JSON: {e}")));
+        }
+    };
+
+    let selected = match path {
+        Some(path) => parse_path(path)?
+            .get(&json)
+            .cloned()
+            .ok_or_else(|| ConsulXError::NotFound(format!("{key} {path}")))?,
+        None => json,
+    };
+    println!("{}", serde_json::to_string_pretty(&selected)?);
+    Ok(())
+}
+
+fn parse_path(path: &str) -> Result<JsonPath> {
+    JsonPath::parse(path).map_err(|e| ConsulXError::InvalidArgument(e.to_string()))
+}
+
+/// Set one field of the JSON document at `key`, CAS-protected.
+pub async fn cmd_set_json(client: &ConsulXClient, key: &str, path: &str, json_str: &str) -> Result<()> {
+    let path = parse_path(path)?;
+    let value: Value = serde_json::from_str(json_str).map_err(|e| {
+        ConsulXError::InvalidArgument(format!("invalid JSON value ({e}); quote strings, e.g. '\"text\"'"))
+    })?;
+
+    let schemas = SchemaRegistry::load(client).await?;
+    let updated = client
+        .kv_update_json(key, DEFAULT_CAS_RETRIES, |doc| {
+            path.set(doc, value.clone())?;
+            Ok(schemas.check(key, doc)?)
+        })
+        .await?;
+    if updated.is_none() {
+        return Err(ConsulXError::NotFound(key.to_string()));
+    }
+    println!("OK (json)");
+    Ok(())
+}
+
+/// Remove one field of the JSON document at `key`, CAS-protected.
+pub async fn cmd_unset_json(client: &ConsulXClient, key: &str, path: &str) -> Result<()> {
+    let path = parse_path(path)?;
+    let schemas = SchemaRegistry::load(client).await?;
+    let mut removed = false;
+
+    let updated = client
+        .kv_update_json(key, DEFAULT_CAS_RETRIES, |doc| {
+            removed = path.remove(doc)?.is_some();
+            Ok(schemas.check(key, doc)?)
+        })
+        .await?;
+    match (updated, removed) {
+        (None, _) => return Err(ConsulXError::NotFound(key.to_string())),
+        (Some(_), true) => println!("OK (json)"),
+        (Some(_), false) => println!("OK (nothing at {path})"),
+    }
+    Ok(())
+}
+
+/// Apply a JSON Merge Patch, or a JSON Patch with `json_patch`, to the
+/// document at `key`, CAS-protected.
+pub async fn cmd_patch(client: &ConsulXClient, key: &str, patch: &str, json_patch: bool) -> Result<()> {
+    let patch: Value = serde_json::from_str(patch)
+        .map_err(|e| ConsulXError::InvalidArgument(format!("patch is not valid JSON: {e}")))?;
+
+    let schemas = SchemaRegistry::load(client).await?;
+    let updated = if json_patch {
+        client.kv_json_patch(key, &patch, &schemas, DEFAULT_CAS_RETRIES).await?
+    } else {
+        client.kv_merge_patch(key, &patch, &schemas, DEFAULT_CAS_RETRIES).await?
+    };
+    if updated.is_none() {
+        return Err(ConsulXError::NotFound(key.to_string()));
     }
+    println!("OK (json)");
     Ok(())
 }
 
 /// JSON-aware PUT: validates JSON, then stores minified JSON
 pub async fn cmd_put_json(client: &ConsulXClient, key: &str, json_str: &str) -> Result<()> {
     let json: Value = serde_json::from_str(json_str)?;
+    SchemaRegistry::load(client).await?.check(key, &json)?;
     let minified = serde_json::to_string(&json)?;
     client.kv_put(key, &minified).await?;
     println!("OK (json)");
@@ -76,15 +235,23 @@ pub async fn cmd_tree(client: &ConsulXClient, prefix: &str) -> Result<()> {
         return Ok(());
     }
 
+    print_tree(prefix, keys.into_iter().map(|k| (k, String::new())));
+    Ok(())
+}
+
+/// Print `(key, label)` pairs as an ASCII tree relative to `prefix`; each
+/// leaf is followed by its label (empty for a plain listing).
+pub(crate) fn print_tree(prefix: &str, entries: impl IntoIterator<Item = (String, String)>) {
     #[derive(Default)]
     struct Node {
         children: BTreeMap<String, Node>,
         is_leaf: bool,
+        label: String,
     }
 
     let mut root = Node::default();
 
-    for key in keys {
+    for (key, label) in entries {
         let rel = if prefix.is_empty() {
             key.clone()
         } else {
@@ -101,13 +268,15 @@ pub async fn cmd_tree(client: &ConsulXClient, prefix: &str) -> Result<()> {
             cur = cur.children.entry((*part).to_string()).or_default();
             if i == parts.len() - 1 {
                 cur.is_leaf = true;
+                cur.label = label.clone();
             }
         }
     }
 
     fn print_node(node: &Node, name: &str, prefix: &str, is_last: bool) {
         let connector = if is_last { "└── " } else { "├── " };
-        println!("{}{}{}", prefix, connector, name);
+        let label = if node.is_leaf { node.label.as_str() } else { "" };
+        println!("{}{}{}{}", prefix, connector, name, label);
 
         let mut children_iter = node.children.iter().peekable();
         while let Some((child_name, child_node)) = children_iter.next() {
@@ -123,49 +292,4 @@ pub async fn cmd_tree(client: &ConsulXClient, prefix: &str) -> Result<()> {
         let is_last = iter.peek().is_none();
         print_node(node, name, "", is_last);
     }
-
-    Ok(())
-}
-
-/// Edit key using $EDITOR, write back on save
-pub async fn cmd_edit(client: &ConsulXClient, key: &str) -> Result<()> {
-    let current = client.kv_get_raw(key).await?.unwrap_or_default();
-
-    // temp file path
-    let mut path = std::env::temp_dir();
-    let ts = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-    path.push(format!("consulx-{}-{}.tmp", key.replace('/', "_"), ts));
-
-    // write current value
-    fs::write(&path, &current)?;
-
-    // pick editor; support values with args, e.g. EDITOR="code -w"
-    let editor = std::env::var("EDITOR").unwrap_or_else(|_| "nano".to_string());
-    let mut editor_parts = editor.split_whitespace();
-    let program = editor_parts.next().unwrap_or("nano");
-
-    // launch editor (blocking)
-    let status = ProcCommand::new(program)
-        .args(editor_parts)
-        .arg(&path)
-        .status()?;
-
-    if !status.success() {
-        eprintln!("Editor exited with non-zero status, aborting update");
-        fs::remove_file(&path).ok();
-        return Ok(());
-    }
-
-    // read new value and only write back if it actually changed
-    let new_val = fs::read_to_string(&path)?;
-    fs::remove_file(&path).ok();
-
-    if new_val == current {
-        println!("No changes");
-    } else {
-        client.kv_put(key, &new_val).await?;
-        println!("OK (edited)");
-    }
-
-    Ok(())
 }
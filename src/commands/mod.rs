@@ -1,2 +1,121 @@
+pub mod backup;
+pub mod diff;
+pub mod edit;
+pub mod export;
 pub mod kv;
+pub mod search;
+pub mod session;
+pub mod sync;
+pub mod transfer;
+pub mod txn;
 pub mod watch;
+
+use crate::client::ConsulXClient;
+use crate::errors::{ConsulXError, Result};
+use crate::parser::Command;
+use crate::transfer::TransferMode;
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Scripts currently running; their input owns stdin, so nothing may prompt.
+static SCRIPTS_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Marks a script as running until dropped; prompts fail meanwhile.
+pub(crate) struct ScriptGuard(());
+
+impl ScriptGuard {
+    pub(crate) fn new() -> Self {
+        SCRIPTS_RUNNING.fetch_add(1, Ordering::SeqCst);
+        ScriptGuard(())
+    }
+}
+
+impl Drop for ScriptGuard {
+    fn drop(&mut self) {
+        SCRIPTS_RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Print `question` and read one line of answer, without the line ending.
+/// Fails instead when stdin isn't a terminal or a script is reading it,
+/// since the answer would be the script's next line (or never come).
+pub fn ask(question: &str) -> Result<String> {
+    if SCRIPTS_RUNNING.load(Ordering::SeqCst) > 0 || !std::io::stdin().is_terminal() {
+        return Err(ConsulXError::InvalidArgument(format!(
+            "can't ask \"{question}\" without a terminal; pass --yes to skip confirmation"
+        )));
+    }
+    print!("{question} ");
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Ask a yes/no question on the terminal; anything but `y`/`yes` declines.
+pub fn confirm(question: &str) -> Result<bool> {
+    let answer = ask(&format!("{question} [y/N]"))?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Make the user retype `expected` (e.g. a prefix about to be deleted).
+pub fn confirm_typed(question: &str, expected: &str) -> Result<bool> {
+    Ok(ask(&format!("{question} Type '{expected}' to confirm:"))? == expected)
+}
+
+/// Run a single stateless command. Commands that need state carried across
+/// lines (transactions, held locks) are handled by the REPL itself and are
+/// rejected here.
+pub async fn execute(client: &ConsulXClient, cmd: Command) -> Result<()> {
+    match cmd {
+        Command::Empty => Ok(()),
+        Command::Help => {
+            crate::repl::print_help();
+            Ok(())
+        }
+        Command::Get { key, as_format } => kv::cmd_get(client, &key, as_format).await,
+        Command::Stat { key } => kv::cmd_stat(client, &key).await,
+        Command::Put { key, value, cas, from, verbatim } => {
+            let value = kv::encode_put_value(&value, from, verbatim)?;
+            kv::cmd_put(client, &key, &value, cas).await
+        }
+        Command::Delete { key, cas } => kv::cmd_delete(client, &key, cas).await,
+        Command::DeleteTree { prefix, yes } => kv::cmd_delete_tree(client, &prefix, yes).await,
+        Command::List { prefix } => kv::cmd_list(client, &prefix).await,
+        Command::Watch { key } => watch::cmd_watch_key(client, &key).await,
+        Command::WatchPrefix { prefix } => watch::cmd_watch_prefix(client, &prefix).await,
+        Command::Tree { prefix } => kv::cmd_tree(client, &prefix).await,
+        Command::GetJson { key, path } => kv::cmd_get_json(client, &key, path.as_deref()).await,
+        Command::SetJson { key, path, json } => kv::cmd_set_json(client, &key, &path, &json).await,
+        Command::UnsetJson { key, path } => kv::cmd_unset_json(client, &key, &path).await,
+        Command::Patch { key, patch, json_patch } => kv::cmd_patch(client, &key, &patch, json_patch).await,
+        Command::PutJson { key, json } => kv::cmd_put_json(client, &key, &json).await,
+        Command::Edit { key, as_format } => edit::cmd_edit(client, &key, as_format).await,
+        Command::Export { prefix, file } => export::cmd_export(client, &prefix, file.as_deref()).await,
+        Command::Import { file, rewrite } => export::cmd_import(client, &file, rewrite.as_ref()).await,
+        Command::Backup { prefix, file } => backup::cmd_backup(client, &prefix, &file).await,
+        Command::Restore { file, yes } => backup::cmd_restore(client, &file, yes).await,
+        Command::Push { dir, prefix, prune, yes } => sync::cmd_push(client, &dir, &prefix, prune, yes).await,
+        Command::Pull { prefix, dir, prune, yes } => sync::cmd_pull(client, &prefix, &dir, prune, yes).await,
+        Command::Grep { pattern, prefix, ignore_case } => {
+            search::cmd_grep(client, &pattern, &prefix, ignore_case).await
+        }
+        Command::Find { prefix, key_regex, json_path, eq } => {
+            search::cmd_find(client, &prefix, key_regex.as_deref(), json_path.as_deref(), eq.as_deref()).await
+        }
+        Command::Copy { src, dst, recursive } => {
+            transfer::cmd_transfer(client, &src, &dst, recursive, TransferMode::Copy).await
+        }
+        Command::Move { src, dst, recursive } => {
+            transfer::cmd_transfer(client, &src, &dst, recursive, TransferMode::Move).await
+        }
+        Command::Diff { target } => diff::cmd_diff(client, &target).await,
+        Command::Sessions => session::cmd_sessions(client).await,
+        Command::Begin => Err(ConsulXError::ReplOnly("begin")),
+        Command::Commit => Err(ConsulXError::ReplOnly("commit")),
+        Command::Abort => Err(ConsulXError::ReplOnly("abort")),
+        Command::Lock { .. } => Err(ConsulXError::ReplOnly("lock")),
+        Command::Unlock { .. } => Err(ConsulXError::ReplOnly("unlock")),
+    }
+}
@@ -0,0 +1,169 @@
+use crate::client::{ConsulXClient, KvPair};
+use crate::errors::{ConsulXError, Result};
+use crate::jsonpath::JsonPath;
+use colored::Colorize;
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+
+fn compile(pattern: &str, ignore_case: bool) -> Result<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| ConsulXError::InvalidArgument(format!("invalid regex '{pattern}': {e}")))
+}
+
+/// 1-based line numbers and text of every line in `text` matching `re`.
+fn matching_lines<'a>(text: &'a str, re: &Regex) -> Vec<(usize, &'a str)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| re.is_match(line))
+        .map(|(n, line)| (n + 1, line))
+        .collect()
+}
+
+/// Search the values under `prefix` and print matching lines per key.
+/// Values that aren't UTF-8 are skipped.
+pub async fn cmd_grep(client: &ConsulXClient, pattern: &str, prefix: &str, ignore_case: bool) -> Result<()> {
+    let re = compile(pattern, ignore_case)?;
+    let (mut keys, mut lines) = (0, 0);
+
+    for pair in client.kv_get_recurse(prefix).await? {
+        let Some(text) = pair.value.as_deref().and_then(|v| std::str::from_utf8(v).ok()) else {
+            continue;
+        };
+        let hits = matching_lines(text, &re);
+        if hits.is_empty() {
+            continue;
+        }
+
+        println!("{}", pair.key.magenta().bold());
+        for (n, line) in &hits {
+            let highlighted = re.replace_all(line, |caps: &regex::Captures| caps[0].red().bold().to_string());
+            println!("  {}: {highlighted}", n.to_string().dimmed());
+        }
+        keys += 1;
+        lines += hits.len();
+    }
+
+    println!("{lines} matching lines in {keys} keys");
+    Ok(())
+}
+
+/// Structured filter for `find`: every given condition must hold.
+struct FindFilter {
+    key_regex: Option<Regex>,
+    path: Option<JsonPath>,
+    eq: Option<Value>,
+}
+
+impl FindFilter {
+    /// `None` if `pair` is filtered out; otherwise the value at the JSON path,
+    /// if one was given.
+    fn check(&self, pair: &KvPair) -> Option<Option<Value>> {
+        if self.key_regex.as_ref().is_some_and(|re| !re.is_match(&pair.key)) {
+            return None;
+        }
+        if self.path.is_none() && self.eq.is_none() {
+            return Some(None);
+        }
+
+        let doc: Value = serde_json::from_slice(pair.value.as_deref()?).ok()?;
+        let found = match &self.path {
+            Some(path) => path.get(&doc)?.clone(),
+            None => doc,
+        };
+        if self.eq.as_ref().is_some_and(|eq| *eq != found) {
+            return None;
+        }
+        Some(self.path.is_some().then_some(found))
+    }
+}
+
+/// `--eq` takes JSON (`true`, `3`, `"x"`); anything that doesn't parse is
+/// compared as a plain string.
+fn parse_eq(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+/// List keys under `prefix` whose key and/or JSON value match the filters.
+pub async fn cmd_find(
+    client: &ConsulXClient,
+    prefix: &str,
+    key_regex: Option<&str>,
+    json_path: Option<&str>,
+    eq: Option<&str>,
+) -> Result<()> {
+    let filter = FindFilter {
+        key_regex: key_regex.map(|re| compile(re, false)).transpose()?,
+        path: json_path
+            .map(JsonPath::parse)
+            .transpose()
+            .map_err(|e| ConsulXError::InvalidArgument(e.to_string()))?,
+        eq: eq.map(parse_eq),
+    };
+
+    let mut matched = 0;
+    for pair in client.kv_get_recurse(prefix).await? {
+        match filter.check(&pair) {
+            None => continue,
+            Some(None) => println!("{}", pair.key),
+            Some(Some(found)) => println!("{}  {}", pair.key, found.to_string().dimmed()),
+        }
+        matched += 1;
+    }
+
+    println!("{matched} keys matched");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pair(key: &str, value: &str) -> KvPair {
+        KvPair {
+            key: key.into(),
+            value: Some(value.as_bytes().to_vec()),
+            create_index: 1,
+            modify_index: 1,
+            lock_index: 0,
+            flags: 0,
+            session: None,
+        }
+    }
+
+    #[test]
+    fn matching_lines_are_numbered_from_one() {
+        let re = compile(r"db-old\.internal", false).unwrap();
+        let text = "host=a\nupstream=db-old.internal:5432\nport=1";
+        assert_eq!(matching_lines(text, &re), vec![(2, "upstream=db-old.internal:5432")]);
+        assert!(matches!(compile("(", false), Err(ConsulXError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn find_filters_on_key_and_json_path() {
+        let filter = FindFilter {
+            key_regex: Some(compile("^svc/.*/config$", false).unwrap()),
+            path: Some(JsonPath::parse("$.enabled").unwrap()),
+            eq: Some(parse_eq("true")),
+        };
+        assert_eq!(filter.check(&pair("svc/a/config", r#"{"enabled":true}"#)), Some(Some(json!(true))));
+        assert_eq!(filter.check(&pair("svc/b/config", r#"{"enabled":false}"#)), None);
+        assert_eq!(filter.check(&pair("svc/c/config", "not json")), None);
+        assert_eq!(filter.check(&pair("svc/a/other", r#"{"enabled":true}"#)), None);
+    }
+
+    #[test]
+    fn find_without_value_filters_matches_keys_only() {
+        let filter = FindFilter { key_regex: Some(compile("a", false).unwrap()), path: None, eq: None };
+        assert_eq!(filter.check(&pair("a", "\u{0}binary")), Some(None));
+    }
+
+    #[test]
+    fn eq_falls_back_to_string() {
+        assert_eq!(parse_eq("3"), json!(3));
+        assert_eq!(parse_eq("\"x\""), json!("x"));
+        assert_eq!(parse_eq("prod"), json!("prod"));
+    }
+}
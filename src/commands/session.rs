@@ -0,0 +1,63 @@
+use crate::client::ConsulXClient;
+use crate::errors::Result;
+use crate::lock::{ConsulLock, LockOptions};
+use std::collections::HashMap;
+
+/// Take `key` as a lock held for the rest of the REPL session. Does not
+/// wait: if someone else holds it, report the holder and return.
+pub async fn cmd_lock(
+    client: &ConsulXClient,
+    locks: &mut HashMap<String, ConsulLock>,
+    key: &str,
+) -> Result<()> {
+    if locks.contains_key(key) {
+        println!("Already holding lock on '{key}'");
+        return Ok(());
+    }
+
+    let opts = LockOptions {
+        session_name: format!("consulx-repl:{key}"),
+        ..Default::default()
+    };
+    match ConsulLock::try_acquire(client, key, opts).await? {
+        Some(lock) => {
+            println!("LOCKED '{key}' (session {})", lock.session_id());
+            locks.insert(key.to_string(), lock);
+        }
+        None => {
+            let holder = client
+                .kv_get_entry(key)
+                .await?
+                .and_then(|p| p.session)
+                .unwrap_or_else(|| "<unknown>".into());
+            println!("Lock on '{key}' is held by session {holder}");
+        }
+    }
+    Ok(())
+}
+
+pub async fn cmd_unlock(locks: &mut HashMap<String, ConsulLock>, key: &str) -> Result<()> {
+    match locks.remove(key) {
+        Some(lock) => {
+            lock.release().await?;
+            println!("UNLOCKED '{key}'");
+        }
+        None => println!("Not holding a lock on '{key}'"),
+    }
+    Ok(())
+}
+
+pub async fn cmd_sessions(client: &ConsulXClient) -> Result<()> {
+    let sessions = client.session_list().await?;
+    if sessions.is_empty() {
+        println!("<empty>");
+        return Ok(());
+    }
+
+    for s in sessions {
+        let name = if s.name.is_empty() { "-" } else { &s.name };
+        let ttl = if s.ttl.is_empty() { "-" } else { &s.ttl };
+        println!("{}  name={}  node={}  ttl={}  behavior={:?}", s.id, name, s.node, ttl, s.behavior);
+    }
+    Ok(())
+}
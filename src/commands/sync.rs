@@ -0,0 +1,287 @@
+use crate::client::ConsulXClient;
+use crate::commands::confirm;
+use crate::commands::kv::print_tree;
+use crate::errors::{ConsulXError, Result};
+use colored::Colorize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Files (or keys) by path relative to the sync root, `/`-separated.
+pub(crate) type Tree = BTreeMap<String, Vec<u8>>;
+
+/// Relative paths to add, update and delete on the target side.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SyncPlan {
+    pub add: Vec<String>,
+    pub update: Vec<String>,
+    pub delete: Vec<String>,
+    /// Paths only present on the target, kept because `--prune` was not given.
+    pub extra: usize,
+}
+
+impl SyncPlan {
+    pub fn is_noop(&self) -> bool {
+        self.add.is_empty() && self.update.is_empty() && self.delete.is_empty()
+    }
+}
+
+/// Compare `source` with `target` and work out what makes target match.
+pub(crate) fn plan_sync(source: &Tree, target: &Tree, prune: bool) -> SyncPlan {
+    let mut plan = SyncPlan::default();
+
+    for (path, value) in source {
+        match target.get(path) {
+            None => plan.add.push(path.clone()),
+            Some(existing) if existing != value => plan.update.push(path.clone()),
+            Some(_) => {}
+        }
+    }
+
+    for path in target.keys().filter(|p| !source.contains_key(*p)) {
+        if prune {
+            plan.delete.push(path.clone());
+        } else {
+            plan.extra += 1;
+        }
+    }
+    plan
+}
+
+/// `app` and `app/` both mean the `app/` folder; empty stays the root.
+pub(crate) fn folder_prefix(prefix: &str) -> String {
+    if prefix.is_empty() || prefix.ends_with('/') {
+        prefix.to_string()
+    } else {
+        format!("{prefix}/")
+    }
+}
+
+/// Every file under `root`, skipping dot-files and dot-directories (e.g. `.git`).
+fn read_local(root: &Path) -> Result<Tree> {
+    fn walk(dir: &Path, rel: &str, out: &mut Tree) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let rel_path = if rel.is_empty() { name } else { format!("{rel}/{name}") };
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                walk(&entry.path(), &rel_path, out)?;
+            } else if file_type.is_file() {
+                out.insert(rel_path, fs::read(entry.path())?);
+            }
+        }
+        Ok(())
+    }
+
+    let mut out = Tree::new();
+    if root.exists() {
+        walk(root, "", &mut out)?;
+    }
+    Ok(out)
+}
+
+/// Like [`read_local`], but the directory must exist: a mistyped source
+/// would otherwise read as empty and plan to delete everything on `--prune`.
+fn read_source_dir(root: &Path) -> Result<Tree> {
+    if !root.is_dir() {
+        let what = if root.exists() { "is not a directory" } else { "does not exist" };
+        return Err(ConsulXError::InvalidArgument(format!("source '{}' {what}", root.display())));
+    }
+    read_local(root)
+}
+
+/// `--prune` against the whole store is never what anyone means.
+fn check_prune_prefix(prefix: &str, prune: bool) -> Result<()> {
+    if prune && prefix.trim_matches('/').is_empty() {
+        return Err(ConsulXError::InvalidArgument(
+            "refusing to --prune with an empty prefix (it would target every key)".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Every key with a value under `prefix`, relative to it. Folder markers
+/// (keys ending in `/` with no value) are skipped.
+pub(crate) async fn read_remote(client: &ConsulXClient, prefix: &str) -> Result<Tree> {
+    let mut out = Tree::new();
+    for pair in client.kv_get_recurse(prefix).await? {
+        let rel = pair.key.strip_prefix(prefix).unwrap_or(&pair.key).to_string();
+        if rel.is_empty() || rel.ends_with('/') {
+            continue;
+        }
+        out.insert(rel, pair.value.unwrap_or_default());
+    }
+    Ok(out)
+}
+
+/// Map a key-relative path to a file path under `root`, refusing anything
+/// that could escape it (`..`, absolute paths).
+fn local_path(root: &Path, rel: &str) -> Result<PathBuf> {
+    let rel_path = Path::new(rel);
+    let safe = rel_path
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)));
+    if !safe {
+        return Err(ConsulXError::InvalidArgument(format!(
+            "refusing to write key '{rel}' outside the target directory"
+        )));
+    }
+    Ok(root.join(rel_path))
+}
+
+/// Print the plan as a tree (`+` add, `~` update, `-` delete) with totals.
+fn print_plan(plan: &SyncPlan) {
+    let entries = plan
+        .add
+        .iter()
+        .map(|p| (p.clone(), format!(" {}", "(+)".green())))
+        .chain(plan.update.iter().map(|p| (p.clone(), format!(" {}", "(~)".yellow()))))
+        .chain(plan.delete.iter().map(|p| (p.clone(), format!(" {}", "(-)".red()))));
+    print_tree("", entries);
+
+    println!(
+        "{} to add, {} to update, {} to delete",
+        plan.add.len(),
+        plan.update.len(),
+        plan.delete.len()
+    );
+    if plan.extra > 0 {
+        println!("{} only on the target side (use --prune to delete)", plan.extra);
+    }
+}
+
+/// Ask before applying unless `--yes` was given. `false` means abort.
+fn approve(plan: &SyncPlan, yes: bool) -> Result<bool> {
+    print_plan(plan);
+    if plan.is_noop() {
+        println!("Already in sync");
+        return Ok(false);
+    }
+    if !yes && !confirm("Apply these changes?")? {
+        println!("Aborted");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Mirror the files under `dir` to keys under `prefix`.
+pub async fn cmd_push(client: &ConsulXClient, dir: &str, prefix: &str, prune: bool, yes: bool) -> Result<()> {
+    check_prune_prefix(prefix, prune)?;
+    let prefix = folder_prefix(prefix);
+    let local = read_source_dir(Path::new(dir))?;
+    let remote = read_remote(client, &prefix).await?;
+
+    let plan = plan_sync(&local, &remote, prune);
+    if !approve(&plan, yes)? {
+        return Ok(());
+    }
+
+    for rel in plan.add.iter().chain(&plan.update) {
+        client.kv_put_bytes(&format!("{prefix}{rel}"), &local[rel]).await?;
+    }
+    for rel in &plan.delete {
+        client.kv_delete(&format!("{prefix}{rel}")).await?;
+    }
+    println!("OK (pushed {} keys, deleted {})", plan.add.len() + plan.update.len(), plan.delete.len());
+    Ok(())
+}
+
+/// Materialise the keys under `prefix` as files under `dir`.
+pub async fn cmd_pull(client: &ConsulXClient, prefix: &str, dir: &str, prune: bool, yes: bool) -> Result<()> {
+    let prefix = folder_prefix(prefix);
+    let root = Path::new(dir);
+    let remote = read_remote(client, &prefix).await?;
+    let local = read_local(root)?;
+
+    // Validate every target path before touching the filesystem.
+    for rel in remote.keys() {
+        local_path(root, rel)?;
+    }
+
+    let plan = plan_sync(&remote, &local, prune);
+    if !approve(&plan, yes)? {
+        return Ok(());
+    }
+
+    for rel in plan.add.iter().chain(&plan.update) {
+        let path = local_path(root, rel)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, &remote[rel])?;
+    }
+    for rel in &plan.delete {
+        fs::remove_file(local_path(root, rel)?)?;
+    }
+    println!("OK (wrote {} files, deleted {})", plan.add.len() + plan.update.len(), plan.delete.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(items: &[(&str, &str)]) -> Tree {
+        items
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn plan_adds_updates_and_counts_extras() {
+        let source = tree(&[("a", "1"), ("b", "2"), ("c/d", "3")]);
+        let target = tree(&[("a", "1"), ("b", "old"), ("z", "gone")]);
+        let plan = plan_sync(&source, &target, false);
+        assert_eq!(plan.add, vec!["c/d".to_string()]);
+        assert_eq!(plan.update, vec!["b".to_string()]);
+        assert!(plan.delete.is_empty());
+        assert_eq!(plan.extra, 1);
+    }
+
+    #[test]
+    fn plan_prunes_only_when_asked() {
+        let source = tree(&[("a", "1")]);
+        let target = tree(&[("a", "1"), ("z", "gone")]);
+        let plan = plan_sync(&source, &target, true);
+        assert_eq!(plan.delete, vec!["z".to_string()]);
+        assert_eq!(plan.extra, 0);
+        assert!(plan_sync(&source, &source, true).is_noop());
+    }
+
+    #[test]
+    fn folder_prefix_adds_trailing_slash() {
+        assert_eq!(folder_prefix("app"), "app/");
+        assert_eq!(folder_prefix("app/"), "app/");
+        assert_eq!(folder_prefix(""), "");
+    }
+
+    #[test]
+    fn push_refuses_missing_source_and_root_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(read_source_dir(&dir.path().join("typo")).is_err());
+        let file = dir.path().join("file");
+        fs::write(&file, "x").unwrap();
+        assert!(read_source_dir(&file).is_err());
+        assert!(read_source_dir(dir.path()).unwrap().contains_key("file"));
+
+        assert!(check_prune_prefix("", true).is_err());
+        assert!(check_prune_prefix("/", true).is_err());
+        assert!(check_prune_prefix("", false).is_ok());
+        assert!(check_prune_prefix("app/", true).is_ok());
+    }
+
+    #[test]
+    fn local_path_rejects_escapes() {
+        let root = Path::new("/tmp/cfg");
+        assert_eq!(local_path(root, "a/b.json").unwrap(), root.join("a/b.json"));
+        assert!(local_path(root, "../etc/passwd").is_err());
+        assert!(local_path(root, "a/../../x").is_err());
+        assert!(local_path(root, "/etc/passwd").is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use crate::client::ConsulXClient;
+use crate::errors::{ConsulXError, Result};
+use crate::transfer::TransferMode;
+use colored::Colorize;
+
+/// Copy or move a key (or, with `recursive`, every key under a prefix).
+pub async fn cmd_transfer(
+    client: &ConsulXClient,
+    src: &str,
+    dst: &str,
+    recursive: bool,
+    mode: TransferMode,
+) -> Result<()> {
+    let report = client.kv_transfer(src, dst, recursive, mode).await?;
+    if report.total == 0 {
+        return Err(ConsulXError::NotFound(src.to_string()));
+    }
+
+    let verb = match mode {
+        TransferMode::Copy => "copied",
+        TransferMode::Move => "moved",
+    };
+    match report.failed {
+        None if report.atomic => println!("OK ({} keys {verb})", report.done.len()),
+        None => println!("OK ({} keys {verb} in batches)", report.done.len()),
+        Some(failure) => {
+            eprintln!(
+                "{} {} of {} keys {verb}; batch of {} starting at '{}' rolled back",
+                "Transfer incomplete:".red(),
+                report.done.len(),
+                report.total,
+                failure.keys.len(),
+                failure.keys.first().map(String::as_str).unwrap_or("?"),
+            );
+            let remaining = report.total - report.done.len() - failure.keys.len();
+            if remaining > 0 {
+                eprintln!("  {remaining} later keys were not attempted");
+            }
+            let errors = failure.errors.into_iter().map(|(key, what)| format!("[{key}]: {what}")).collect();
+            return Err(ConsulXError::TxnRolledBack { errors });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_agent;
+
+    const ENTRY: &str = r#"[{"Key":"app/db","Value":"eA==","Flags":0,"CreateIndex":1,"ModifyIndex":5,"LockIndex":0}]"#;
+
+    #[tokio::test]
+    async fn rolled_back_move_is_a_txn_error() {
+        let body = r#"{"Results":null,"Errors":[{"OpIndex":1,"What":"index is stale"}]}"#;
+        let agent = test_agent::spawn(vec![(200, ENTRY), (409, body)]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+
+        let err = cmd_transfer(&client, "app/db", "app/db2", false, TransferMode::Move).await.unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert!(err.to_string().contains("[app/db]: index is stale"), "{err}");
+    }
+}
@@ -0,0 +1,77 @@
+use crate::client::ConsulXClient;
+use crate::errors::{ConsulXError, Result};
+use crate::txn::{KvOp, Txn, TxnOutcome};
+
+/// Queue a `put` into the open transaction instead of writing it.
+pub fn stage_put(txn: &mut Txn, key: &str, value: &str, cas: Option<u64>) {
+    match cas {
+        Some(index) => txn.cas(key, value, index),
+        None => txn.set(key, value),
+    };
+    println!("QUEUED ({} ops)", txn.len());
+}
+
+/// Queue a `del` into the open transaction instead of deleting.
+pub fn stage_delete(txn: &mut Txn, key: &str, cas: Option<u64>) {
+    match cas {
+        Some(index) => txn.delete_cas(key, index),
+        None => txn.delete(key),
+    };
+    println!("QUEUED ({} ops)", txn.len());
+}
+
+/// Queue a `del -r` into the open transaction, so it commits (or aborts)
+/// with the rest. An empty prefix is refused as it is outside a transaction.
+pub fn stage_delete_tree(txn: &mut Txn, prefix: &str) -> Result<()> {
+    if prefix.trim_matches('/').is_empty() {
+        return Err(ConsulXError::InvalidArgument("refusing to recursively delete an empty prefix".into()));
+    }
+    txn.delete_tree(prefix);
+    println!("QUEUED ({} ops)", txn.len());
+    Ok(())
+}
+
+/// Submit the staged transaction; a rollback is an error listing each
+/// failed op.
+pub async fn cmd_commit(client: &ConsulXClient, txn: &Txn) -> Result<()> {
+    if txn.is_empty() {
+        println!("Nothing to commit");
+        return Ok(());
+    }
+
+    match client.txn_commit(txn).await? {
+        TxnOutcome::Committed(_) => {
+            println!("OK ({} ops committed)", txn.len());
+            Ok(())
+        }
+        TxnOutcome::RolledBack(errors) => {
+            let errors = errors
+                .into_iter()
+                .map(|err| {
+                    let key = txn.ops().get(err.op_index).map(KvOp::key).unwrap_or("?");
+                    format!("op #{} [{}]: {}", err.op_index, key, err.what)
+                })
+                .collect();
+            Err(ConsulXError::TxnRolledBack { errors })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_agent;
+
+    #[tokio::test]
+    async fn rolled_back_commit_is_an_error() {
+        let body = r#"{"Results":null,"Errors":[{"OpIndex":0,"What":"index is stale"}]}"#;
+        let agent = test_agent::spawn(vec![(409, body)]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+        let mut txn = Txn::new();
+        txn.cas("app/db", "x", 3);
+
+        let err = cmd_commit(&client, &txn).await.unwrap_err();
+        assert_eq!(err.exit_code(), 5);
+        assert!(err.to_string().contains("op #0 [app/db]: index is stale"), "{err}");
+    }
+}
@@ -1,30 +1,55 @@
-use crate::client::ConsulXClient;
-use crate::errors::Result;
+use crate::client::{ConsulXClient, reconcile};
+use crate::errors::{ClientResult, Result};
+use colored::Colorize;
 use std::time::Duration;
 
-/// Consul blocking queries return the same `X-Consul-Index` when nothing has
-/// changed (the query simply timed out), and a *smaller* index means the
-/// table was reset — in which case we must restart from index 0. This helper
-/// applies that reconciliation and reports whether the state actually changed.
-fn reconcile(prev: Option<u64>, mut new_index: u64) -> (u64, bool) {
-    if let Some(prev) = prev {
-        if new_index < prev {
-            new_index = 0; // reset per Consul blocking-query guidance
+/// Pause after a watch request fails for good before polling again.
+const ERROR_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Pass a blocking-query result through, or — for a transient failure the
+/// client's own retries didn't get past — report it, wait, and return
+/// `None` so the watch carries on from the same index.
+async fn survive<T>(result: ClientResult<T>) -> Result<Option<T>> {
+    match result {
+        Ok(v) => Ok(Some(v)),
+        Err(e) if e.is_transient() => {
+            eprintln!("{} {e} (retrying in {}s)", "Watch error:".yellow(), ERROR_BACKOFF.as_secs());
+            tokio::time::sleep(ERROR_BACKOFF).await;
+            Ok(None)
         }
-        (new_index, new_index != prev)
-    } else {
-        (new_index, true) // first observation always prints
+        Err(e) => Err(e.into()),
     }
 }
 
+/// Notice the client having failed over since `endpoint` was last seen.
+/// Compares endpoint positions, since display addresses needn't be unique.
+fn failed_over(client: &ConsulXClient, endpoint: &mut usize, index: Option<u64>) -> bool {
+    let now = client.current_endpoint();
+    if now == *endpoint {
+        return false;
+    }
+    eprintln!(
+        "{} now watching via {}, resuming from index {}",
+        "Failover:".yellow(),
+        client.address(),
+        index.unwrap_or(0)
+    );
+    *endpoint = now;
+    true
+}
+
 pub async fn cmd_watch_key(client: &ConsulXClient, key: &str) -> Result<()> {
     println!("Watching key '{key}' (Ctrl+C to stop)...");
 
     let mut index: Option<u64> = None;
+    let mut endpoint = client.current_endpoint();
 
     loop {
-        let (raw_index, value) = client.kv_watch(key, index).await?;
-        let (new_index, changed) = reconcile(index, raw_index);
+        let Some((raw_index, value)) = survive(client.kv_watch(key, index).await).await? else {
+            continue;
+        };
+        let switched = failed_over(client, &mut endpoint, index);
+        let (new_index, changed) = reconcile(index, raw_index, switched);
 
         if changed {
             match value {
@@ -47,10 +72,14 @@ pub async fn cmd_watch_prefix(client: &ConsulXClient, prefix: &str) -> Result<()
     println!("Watching prefix '{prefix}' (Ctrl+C to stop)...");
 
     let mut index: Option<u64> = None;
+    let mut endpoint = client.current_endpoint();
 
     loop {
-        let (raw_index, keys) = client.kv_watch_prefix(prefix, index).await?;
-        let (new_index, changed) = reconcile(index, raw_index);
+        let Some((raw_index, keys)) = survive(client.kv_watch_prefix(prefix, index).await).await? else {
+            continue;
+        };
+        let switched = failed_over(client, &mut endpoint, index);
+        let (new_index, changed) = reconcile(index, raw_index, switched);
 
         if changed {
             println!("UPDATED PREFIX [{prefix}]:");
@@ -73,33 +102,41 @@ pub async fn cmd_watch_prefix(client: &ConsulXClient, prefix: &str) -> Result<()
 
 #[cfg(test)]
 mod tests {
-    use super::reconcile;
+    use crate::client::reconcile;
 
     #[test]
     fn first_observation_always_changes() {
-        assert_eq!(reconcile(None, 42), (42, true));
-        assert_eq!(reconcile(None, 0), (0, true));
+        assert_eq!(reconcile(None, 42, false), (42, true));
+        assert_eq!(reconcile(None, 0, false), (0, true));
     }
 
     #[test]
     fn same_index_is_no_change() {
         // blocking query timed out with nothing new
-        assert_eq!(reconcile(Some(42), 42), (42, false));
+        assert_eq!(reconcile(Some(42), 42, false), (42, false));
     }
 
     #[test]
     fn advancing_index_is_a_change() {
-        assert_eq!(reconcile(Some(42), 43), (43, true));
+        assert_eq!(reconcile(Some(42), 43, false), (43, true));
     }
 
     #[test]
     fn backwards_index_resets_to_zero_and_changes() {
         // Consul was reset; restart the cursor from 0
-        assert_eq!(reconcile(Some(100), 5), (0, true));
+        assert_eq!(reconcile(Some(100), 5, false), (0, true));
     }
 
     #[test]
     fn backwards_to_zero_still_resets() {
-        assert_eq!(reconcile(Some(100), 0), (0, true));
+        assert_eq!(reconcile(Some(100), 0, false), (0, true));
+    }
+
+    #[test]
+    fn lagging_agent_after_failover_keeps_the_index() {
+        assert_eq!(reconcile(Some(100), 97, true), (100, false));
+        // but a newer index from it is a real change
+        assert_eq!(reconcile(Some(100), 104, true), (104, true));
+        assert_eq!(reconcile(None, 7, true), (7, true));
     }
 }
@@ -0,0 +1,334 @@
+use anyhow::Result;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::{Notify, watch};
+use tokio::task::JoinHandle;
+
+use crate::client::{ConsulXClient, KvPair, reconcile};
+use crate::errors::ClientResult;
+use crate::lock::{LockOptions, renew_loop};
+use crate::session::{SessionBehavior, SessionOptions};
+
+/// Contends for leadership of a key among any number of replicas.
+///
+/// A background task holds a session, tries to acquire `key` whenever it is
+/// vacant, and follows the key with blocking queries so that a lost lock or
+/// an invalidated session is noticed and contended for again.
+///
+/// ```no_run
+/// # async fn demo(client: consulx::ConsulXClient) -> consulx::ClientResult<()> {
+/// use consulx::{LeaderElection, LockOptions};
+///
+/// let opts = LockOptions { value: b"replica-1".to_vec(), ..Default::default() };
+/// let election = LeaderElection::start(&client, "service/worker/leader", opts)?;
+/// let mut leading = election.subscribe();
+/// while leading.changed().await.is_ok() {
+///     println!("leader: {}", *leading.borrow());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct LeaderElection {
+    is_leader: watch::Receiver<bool>,
+    leader: watch::Receiver<Option<String>>,
+    last_error: watch::Receiver<Option<String>>,
+    stop: Arc<Notify>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl LeaderElection {
+    /// Start contending for `key`. `opts.value` is written to the key while
+    /// this replica leads, so others can see who the leader is. Fails up
+    /// front if `opts` could never create a session (e.g. a TTL under 10s).
+    pub fn start(client: &ConsulXClient, key: &str, opts: LockOptions) -> ClientResult<Self> {
+        opts.validate()?;
+        let (leader_tx, is_leader) = watch::channel(false);
+        let (value_tx, leader) = watch::channel(None);
+        let (error_tx, last_error) = watch::channel(None);
+        let stop = Arc::new(Notify::new());
+
+        let task = tokio::spawn(
+            Contender {
+                client: client.clone(),
+                key: key.to_string(),
+                opts,
+                session: None,
+                is_leader: leader_tx,
+                leader: value_tx,
+                last_error: error_tx,
+            }
+            .run(stop.clone()),
+        );
+
+        Ok(Self {
+            is_leader,
+            leader,
+            last_error,
+            stop,
+            task: Some(task),
+        })
+    }
+
+    pub fn is_leader(&self) -> bool {
+        *self.is_leader.borrow()
+    }
+
+    /// A channel that flips whenever this replica gains or loses leadership.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.is_leader.clone()
+    }
+
+    /// The value the current leader wrote to the key, `None` while vacant.
+    pub fn leader(&self) -> Option<String> {
+        self.leader.borrow().clone()
+    }
+
+    /// The most recent failure talking to Consul (creating the session,
+    /// acquiring or watching the key), cleared once the key is read again.
+    /// The election keeps retrying; this says why it isn't getting anywhere.
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.borrow().clone()
+    }
+
+    /// Stop contending, releasing the key if held and destroying the session.
+    pub async fn resign(mut self) -> Result<()> {
+        self.stop.notify_one();
+        if let Some(task) = self.task.take() {
+            task.await?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for LeaderElection {
+    fn drop(&mut self) {
+        // The task cleans up after itself once it sees the stop signal.
+        self.stop.notify_one();
+    }
+}
+
+struct Contender {
+    client: ConsulXClient,
+    key: String,
+    opts: LockOptions,
+    session: Option<Session>,
+    is_leader: watch::Sender<bool>,
+    leader: watch::Sender<Option<String>>,
+    last_error: watch::Sender<Option<String>>,
+}
+
+/// Our session, renewed by its own task so the blocking query on the key is
+/// never interrupted; `alive` drops once a renewal finds it gone.
+struct Session {
+    id: String,
+    alive: Arc<AtomicBool>,
+    renewer: JoinHandle<()>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.renewer.abort();
+    }
+}
+
+/// What one read of the key means for this replica.
+#[derive(Debug, PartialEq, Eq)]
+struct Observation {
+    vacant: bool,
+    leading: bool,
+    /// The value written by the current leader, if there is one.
+    leader: Option<String>,
+}
+
+fn observe(entry: Option<KvPair>, session: Option<&str>) -> Observation {
+    let holder = entry.as_ref().and_then(|p| p.session.as_deref());
+    let leading = holder.is_some() && holder == session;
+    match holder {
+        None => Observation { vacant: true, leading, leader: None },
+        Some(_) => Observation { vacant: false, leading, leader: entry.and_then(|p| p.value_str()) },
+    }
+}
+
+impl Contender {
+    async fn run(mut self, stop: Arc<Notify>) {
+        let mut index: Option<u64> = None;
+        let mut vacant = true;
+        let mut endpoint = self.client.current_endpoint();
+        let (client, key) = (self.client.clone(), self.key.clone());
+
+        loop {
+            if self.session.as_ref().is_some_and(|s| !s.alive.load(Ordering::SeqCst)) {
+                // Session invalidated: any lock it held is gone with it.
+                self.session = None;
+                set(&self.is_leader, false);
+            }
+            if self.session.is_none() {
+                match self.client.session_create(&self.session_options()).await {
+                    Ok(id) => self.session = Some(self.start_session(id)),
+                    Err(e) => {
+                        set(&self.last_error, Some(format!("creating session: {e}")));
+                        tokio::select! {
+                            _ = stop.notified() => return self.shutdown().await,
+                            _ = tokio::time::sleep(Duration::from_secs(2)) => continue,
+                        }
+                    }
+                }
+            }
+
+            if vacant {
+                self.contend().await;
+            }
+
+            tokio::select! {
+                _ = stop.notified() => return self.shutdown().await,
+                res = client.kv_watch_entry(&key, index) => match res {
+                    Ok((raw_index, entry)) => {
+                        set(&self.last_error, None);
+                        let switched = client.current_endpoint() != endpoint;
+                        endpoint = client.current_endpoint();
+                        let (new_index, _) = reconcile(index, raw_index, switched);
+                        index = Some(new_index);
+
+                        let seen = observe(entry, self.session.as_ref().map(|s| s.id.as_str()));
+                        vacant = seen.vacant;
+                        set(&self.is_leader, seen.leading);
+                        set(&self.leader, seen.leader);
+
+                        if new_index == 0 {
+                            tokio::time::sleep(Duration::from_secs(2)).await;
+                        }
+                    }
+                    Err(e) => {
+                        set(&self.last_error, Some(format!("watching '{key}': {e}")));
+                        tokio::time::sleep(Duration::from_secs(2)).await;
+                    }
+                },
+            }
+        }
+    }
+
+    fn session_options(&self) -> SessionOptions {
+        SessionOptions {
+            name: Some(self.opts.session_name.clone()),
+            ttl: Some(self.opts.ttl),
+            behavior: SessionBehavior::Release,
+            ..Default::default()
+        }
+    }
+
+    fn start_session(&self, id: String) -> Session {
+        let alive = Arc::new(AtomicBool::new(true));
+        let renewer = tokio::spawn(renew_loop(self.client.clone(), id.clone(), self.opts.ttl / 2, alive.clone()));
+        Session { id, alive, renewer }
+    }
+
+    /// Try to take the vacant key. Lock-delay after a previous holder's
+    /// session died makes this fail for a while, so it is retried each time
+    /// the blocking query returns, at least once per wait period.
+    async fn contend(&mut self) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        match self.client.kv_acquire(&self.key, &self.opts.value, &session.id).await {
+            Ok(true) => {
+                set(&self.is_leader, true);
+                set(&self.leader, Some(String::from_utf8_lossy(&self.opts.value).into_owned()));
+            }
+            Ok(false) => {}
+            Err(e) => set(&self.last_error, Some(format!("acquiring '{}': {e}", self.key))),
+        }
+    }
+
+    async fn shutdown(mut self) {
+        if let Some(session) = self.session.take() {
+            session.renewer.abort();
+            if *self.is_leader.borrow() {
+                self.client.kv_release(&self.key, &self.opts.value, &session.id).await.ok();
+            }
+            self.client.session_destroy(&session.id).await.ok();
+        }
+        set(&self.is_leader, false);
+    }
+}
+
+/// Publish `value`, waking subscribers only when it actually changed.
+fn set<T: PartialEq>(tx: &watch::Sender<T>, value: T) {
+    tx.send_if_modified(|cur| {
+        if *cur == value {
+            false
+        } else {
+            *cur = value;
+            true
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(value: &str, session: Option<&str>) -> KvPair {
+        KvPair {
+            key: "service/leader".into(),
+            value: Some(value.as_bytes().to_vec()),
+            create_index: 1,
+            modify_index: 2,
+            lock_index: 1,
+            flags: 0,
+            session: session.map(String::from),
+        }
+    }
+
+    #[test]
+    fn holding_the_key_makes_us_leader() {
+        let seen = observe(Some(entry("replica-1", Some("s1"))), Some("s1"));
+        assert_eq!(seen, Observation { vacant: false, leading: true, leader: Some("replica-1".into()) });
+    }
+
+    #[test]
+    fn another_holder_makes_us_follower() {
+        let seen = observe(Some(entry("replica-2", Some("s2"))), Some("s1"));
+        assert_eq!(seen, Observation { vacant: false, leading: false, leader: Some("replica-2".into()) });
+    }
+
+    #[test]
+    fn released_or_missing_key_is_vacant() {
+        // a released key keeps its last value, but nobody leads
+        let seen = observe(Some(entry("replica-1", None)), Some("s1"));
+        assert_eq!(seen, Observation { vacant: true, leading: false, leader: None });
+        assert_eq!(observe(None, Some("s1")), Observation { vacant: true, leading: false, leader: None });
+    }
+
+    #[test]
+    fn losing_our_session_ends_leadership() {
+        // the key still shows our old session until Consul catches up
+        let seen = observe(Some(entry("replica-1", Some("s1"))), None);
+        assert!(!seen.leading && !seen.vacant);
+    }
+
+    #[tokio::test]
+    async fn bad_ttl_is_rejected_up_front() {
+        let client = ConsulXClient::with_options("http://127.0.0.1:8500", None, None).unwrap();
+        let opts = LockOptions { ttl: Duration::from_secs(1), ..Default::default() };
+        let err = LeaderElection::start(&client, "service/leader", opts).err().unwrap();
+        assert!(matches!(err, crate::ClientError::InvalidRequest(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn session_failures_are_exposed() {
+        let agent = crate::client::test_agent::spawn(vec![(403, "Permission denied")]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+        let election = LeaderElection::start(&client, "service/leader", LockOptions::default()).unwrap();
+
+        let mut last_error = election.last_error.clone();
+        tokio::time::timeout(Duration::from_secs(5), last_error.wait_for(Option::is_some))
+            .await
+            .unwrap()
+            .unwrap();
+        let err = election.last_error().unwrap();
+        assert!(err.starts_with("creating session: permission denied (403)"), "{err}");
+        assert!(!election.is_leader());
+        election.resign().await.unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+use reqwest::{Client, Request, Url};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// How long an endpoint that failed is passed over while others are healthy.
+pub const ENDPOINT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// One agent address with its own connection pool (and Unix socket, which
+/// reqwest fixes per client).
+#[derive(Debug)]
+pub struct Endpoint {
+    /// Normalized base URL, no trailing slash.
+    pub base: String,
+    /// Set for `unix://` addresses; `base` is then a placeholder host.
+    pub unix_socket: Option<PathBuf>,
+    pub(crate) http: Client,
+    down_until: Mutex<Option<Instant>>,
+}
+
+impl Endpoint {
+    pub(crate) fn new(base: String, unix_socket: Option<PathBuf>, http: Client) -> Self {
+        Self { base, unix_socket, http, down_until: Mutex::new(None) }
+    }
+
+    /// The address as configured, e.g. for "Connecting to ..." output.
+    pub fn address(&self) -> String {
+        match &self.unix_socket {
+            Some(path) => format!("unix://{}", path.display()),
+            None => self.base.clone(),
+        }
+    }
+
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.down_until.lock().unwrap().is_none_or(|until| now >= until)
+    }
+
+    /// Point `req`, built against `from_base`, at this endpoint instead.
+    pub(crate) fn rebase(&self, mut req: Request, from_base: &str) -> Request {
+        if self.base != from_base
+            && let Some(rest) = req.url().as_str().strip_prefix(from_base)
+            && let Ok(url) = Url::parse(&format!("{}{rest}", self.base))
+        {
+            *req.url_mut() = url;
+        }
+        req
+    }
+}
+
+/// The agents a client can talk to. Requests stick to the current one
+/// until it fails, then move to the next healthy one in order.
+#[derive(Debug)]
+pub(crate) struct Endpoints {
+    list: Vec<Endpoint>,
+    current: AtomicUsize,
+}
+
+impl Endpoints {
+    pub(crate) fn new(list: Vec<Endpoint>) -> Self {
+        assert!(!list.is_empty(), "a client needs at least one endpoint");
+        Self { list, current: AtomicUsize::new(0) }
+    }
+
+    pub(crate) fn list(&self) -> &[Endpoint] {
+        &self.list
+    }
+
+    pub(crate) fn current_index(&self) -> usize {
+        self.current.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn current(&self) -> &Endpoint {
+        &self.list[self.current_index()]
+    }
+
+    /// Choose the endpoint for the next try, skipping those already
+    /// `tried` for this request: the current one if healthy, else the next
+    /// healthy one, else the next untried one at all.
+    pub(crate) fn pick(&self, tried: &[bool], now: Instant) -> usize {
+        let start = self.current.load(Ordering::Relaxed);
+        let n = self.list.len();
+        let untried = (0..n).map(|k| (start + k) % n).filter(|i| !tried[*i]);
+
+        let chosen = untried
+            .clone()
+            .find(|i| self.list[*i].is_healthy(now))
+            .or_else(|| untried.clone().next())
+            .unwrap_or(start);
+        self.current.store(chosen, Ordering::Relaxed);
+        chosen
+    }
+
+    /// Whether some endpoint hasn't been tried for this request yet.
+    pub(crate) fn any_untried(&self, tried: &[bool]) -> bool {
+        tried.iter().any(|t| !t)
+    }
+
+    pub(crate) fn mark_down(&self, i: usize, now: Instant) {
+        *self.list[i].down_until.lock().unwrap() = Some(now + ENDPOINT_COOLDOWN);
+    }
+
+    pub(crate) fn mark_up(&self, i: usize) {
+        *self.list[i].down_until.lock().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(n: usize) -> Endpoints {
+        Endpoints::new((0..n).map(|i| Endpoint::new(format!("http://10.0.0.{i}:8500"), None, Client::new())).collect())
+    }
+
+    #[test]
+    fn selection_sticks_until_a_failure() {
+        let eps = endpoints(3);
+        let now = Instant::now();
+        assert_eq!(eps.pick(&[false; 3], now), 0);
+        assert_eq!(eps.pick(&[false; 3], now), 0);
+
+        // 0 fails: move on to 1, and stay there afterwards
+        eps.mark_down(0, now);
+        assert_eq!(eps.pick(&[true, false, false], now), 1);
+        assert_eq!(eps.pick(&[false; 3], now), 1);
+
+        // 0 recovering doesn't pull requests back
+        assert_eq!(eps.pick(&[false; 3], now + ENDPOINT_COOLDOWN), 1);
+    }
+
+    #[test]
+    fn unhealthy_endpoints_are_skipped_during_cooldown() {
+        let eps = endpoints(3);
+        let now = Instant::now();
+        eps.mark_down(0, now);
+        eps.mark_down(1, now);
+        assert_eq!(eps.pick(&[true, false, false], now), 2);
+
+        // with every other endpoint tried, a cooling-down one is still used
+        eps.mark_down(2, now);
+        assert_eq!(eps.pick(&[false, true, true], now), 0);
+        assert!(!eps.any_untried(&[true, true, true]));
+
+        eps.mark_up(1);
+        assert_eq!(eps.pick(&[true, false, true], now), 1);
+    }
+
+    #[test]
+    fn requests_are_rebased_onto_the_endpoint() {
+        let eps = endpoints(2);
+        let url = Url::parse("http://10.0.0.0:8500/v1/kv/app/db?raw=true&dc=dc1").unwrap();
+        let req = eps.list()[1].rebase(Request::new(reqwest::Method::GET, url), "http://10.0.0.0:8500");
+        assert_eq!(req.url().as_str(), "http://10.0.0.1:8500/v1/kv/app/db?raw=true&dc=dc1");
+    }
+}
@@ -1,5 +1,10 @@
 use thiserror::Error;
+use std::error::Error as _;
+use std::fmt;
 use std::io;
+use std::time::Duration;
+
+use crate::schema::SchemaViolation;
 
 #[derive(Debug, Error)]
 pub enum ConsulXError {
@@ -9,12 +14,227 @@ pub enum ConsulXError {
     #[error("Missing argument: {0}")]
     MissingArgument(&'static str),
 
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("Key not found: {0}")]
+    NotFound(String),
+
+    #[error("'{0}' is only available in the interactive REPL")]
+    ReplOnly(&'static str),
+
+    #[error("CAS conflict on '{key}': index {index} is stale")]
+    CasConflict { key: String, index: u64 },
+
+    /// A `commit` that Consul rejected as a whole; one line per failed op.
+    #[error("Transaction rolled back:{}", .errors.iter().map(|e| format!("\n  {e}")).collect::<String>())]
+    TxnRolledBack { errors: Vec<String> },
+
+    #[error("Value for '{key}' violates its schema:{}", .violations.iter().map(|v| format!("\n  {v}")).collect::<String>())]
+    SchemaViolation { key: String, violations: Vec<SchemaViolation> },
+
     #[error(transparent)]
-    Other(#[from] anyhow::Error),
+    Client(#[from] ClientError),
+
+    #[error(transparent)]
+    Other(anyhow::Error),
 }
 
 pub type Result<T> = std::result::Result<T, ConsulXError>;
 
+impl ConsulXError {
+    /// Process exit code for the non-interactive CLI:
+    /// 1 not found, 2 usage error, 3 server (or any other) failure,
+    /// 4 permission denied, 5 CAS conflict or rolled-back transaction,
+    /// 6 Consul unreachable,
+    /// 7 rate limited.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ConsulXError::NotFound(_) => 1,
+            ConsulXError::UnknownCommand(_)
+            | ConsulXError::MissingArgument(_)
+            | ConsulXError::InvalidArgument(_)
+            | ConsulXError::ReplOnly(_)
+            | ConsulXError::SchemaViolation { .. } => 2,
+            ConsulXError::CasConflict { .. } | ConsulXError::TxnRolledBack { .. } => 5,
+            ConsulXError::Client(e) => e.exit_code(),
+            ConsulXError::Other(_) => 3,
+        }
+    }
+
+    /// A short suggestion to print under the error, if there is one.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ConsulXError::Client(e) => e.hint(),
+            _ => None,
+        }
+    }
+}
+
+/// A failed Consul HTTP API call, classified by what the caller can do
+/// about it. Carries whatever error text Consul put in the response body.
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("not found (404){}", fmt_body(.0))]
+    NotFound(String),
+
+    #[error("permission denied ({status}){}", fmt_body(.body))]
+    PermissionDenied { status: u16, body: String },
+
+    #[error("CAS conflict on '{key}': it kept changing concurrently ({attempts} attempts)")]
+    CasConflict { key: String, attempts: u32 },
+
+    #[error("rate limited by Consul (429){}{}", fmt_retry_after(.retry_after), fmt_body(.body))]
+    RateLimited { retry_after: Option<Duration>, body: String },
+
+    #[error("Consul returned status {status}{}", fmt_body(.body))]
+    ServerError { status: u16, body: String },
+
+    #[error("{}", ErrorChain(.0))]
+    Transport(reqwest::Error),
+
+    #[error("unexpected response from Consul: {0}")]
+    Decode(String),
+
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+}
+
+pub type ClientResult<T> = std::result::Result<T, ClientError>;
+
+/// A failed read-modify-write of a JSON value
+/// ([`kv_update_json`](crate::ConsulXClient::kv_update_json) and the patch
+/// helpers built on it). Nothing was written.
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    #[error(transparent)]
+    Client(#[from] ClientError),
+
+    #[error("value at '{key}' is not valid JSON: {reason}")]
+    NotJson { key: String, reason: String },
+
+    /// The update itself refused the document, e.g. a failed JSON Patch
+    /// `test` or a schema violation; carries the update's own error.
+    #[error(transparent)]
+    Rejected(anyhow::Error),
+}
+
+fn fmt_body(body: &str) -> String {
+    match body.trim() {
+        "" => String::new(),
+        body => format!(": {body}"),
+    }
+}
+
+fn fmt_retry_after(retry_after: &Option<Duration>) -> String {
+    retry_after.map_or_else(String::new, |d| format!(", retry after {}s", d.as_secs()))
+}
+
+/// reqwest's own message is just "error sending request for url (...)";
+/// the useful part ("connection refused", a TLS failure) is in the sources.
+struct ErrorChain<'a>(&'a reqwest::Error);
+
+impl fmt::Display for ErrorChain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        let mut source = self.0.source();
+        while let Some(e) = source {
+            write!(f, ": {e}")?;
+            source = e.source();
+        }
+        Ok(())
+    }
+}
+
+impl ClientError {
+    /// Classify a non-success response by status code.
+    pub fn from_status(status: u16, body: impl Into<String>, retry_after: Option<Duration>) -> Self {
+        let body = body.into().trim().to_string();
+        match status {
+            400 => ClientError::InvalidRequest(if body.is_empty() { "400 Bad Request".into() } else { body }),
+            401 | 403 => ClientError::PermissionDenied { status, body },
+            404 => ClientError::NotFound(body),
+            429 => ClientError::RateLimited { retry_after, body },
+            _ => ClientError::ServerError { status, body },
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ClientError::NotFound(_) => 1,
+            ClientError::InvalidRequest(_) => 2,
+            ClientError::ServerError { .. } | ClientError::Decode(_) => 3,
+            ClientError::PermissionDenied { .. } => 4,
+            ClientError::CasConflict { .. } => 5,
+            ClientError::Transport(_) => 6,
+            ClientError::RateLimited { .. } => 7,
+        }
+    }
+
+    /// Worth trying again later: Consul was unreachable, overloaded or
+    /// briefly failing, as opposed to refusing the request itself.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ClientError::Transport(_) | ClientError::RateLimited { .. } => true,
+            ClientError::ServerError { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
+
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ClientError::PermissionDenied { .. } => {
+                Some("check that CONSUL_HTTP_TOKEN (or --token) grants access to this path")
+            }
+            ClientError::Transport(_) => Some("check CONSUL_HTTP_ADDR (or --addr), the TLS settings and that the agent is running"),
+            ClientError::RateLimited { .. } => Some("wait a moment and try again"),
+            ClientError::CasConflict { .. } => Some("re-read the key and try again"),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ClientError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            ClientError::Decode(ErrorChain(&e).to_string())
+        } else {
+            ClientError::Transport(e)
+        }
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(e: serde_json::Error) -> Self {
+        ClientError::Decode(e.to_string())
+    }
+}
+
+// A ConsulXError or ClientError that travelled through an anyhow::Error
+// (e.g. out of a closure passed to the client) comes back out as itself.
+impl From<anyhow::Error> for ConsulXError {
+    fn from(e: anyhow::Error) -> Self {
+        let e = match e.downcast::<ConsulXError>() {
+            Ok(inner) => return inner,
+            Err(e) => e,
+        };
+        match e.downcast::<ClientError>() {
+            Ok(inner) => ConsulXError::Client(inner),
+            Err(e) => ConsulXError::Other(e),
+        }
+    }
+}
+
+impl From<UpdateError> for ConsulXError {
+    fn from(e: UpdateError) -> Self {
+        match e {
+            UpdateError::Client(e) => ConsulXError::Client(e),
+            UpdateError::Rejected(e) => e.into(),
+            e @ UpdateError::NotJson { .. } => ConsulXError::Other(e.into()),
+        }
+    }
+}
+
 // Allow `?` on serde_json errors
 impl From<serde_json::Error> for ConsulXError {
     fn from(e: serde_json::Error) -> Self {
@@ -28,3 +248,51 @@ impl From<io::Error> for ConsulXError {
         ConsulXError::Other(anyhow::Error::new(e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consulx_errors_survive_a_trip_through_anyhow() {
+        let wrapped = anyhow::Error::new(ConsulXError::NotFound("k".into()));
+        assert!(matches!(ConsulXError::from(wrapped), ConsulXError::NotFound(k) if k == "k"));
+        assert!(matches!(ConsulXError::from(anyhow::anyhow!("boom")), ConsulXError::Other(_)));
+
+        let wrapped = anyhow::Error::new(ClientError::CasConflict { key: "k".into(), attempts: 6 });
+        assert_eq!(ConsulXError::from(wrapped).exit_code(), 5);
+    }
+
+    #[test]
+    fn statuses_map_to_error_kinds() {
+        let e = ClientError::from_status(403, "Permission denied: token lacks key:write\n", None);
+        assert!(matches!(&e, ClientError::PermissionDenied { status: 403, body } if body == "Permission denied: token lacks key:write"));
+        assert_eq!(e.to_string(), "permission denied (403): Permission denied: token lacks key:write");
+
+        assert!(matches!(ClientError::from_status(401, "", None), ClientError::PermissionDenied { .. }));
+        assert!(matches!(ClientError::from_status(404, "", None), ClientError::NotFound(_)));
+        assert!(matches!(ClientError::from_status(400, "", None), ClientError::InvalidRequest(b) if b == "400 Bad Request"));
+        assert_eq!(ClientError::from_status(500, "rpc error", None).to_string(), "Consul returned status 500: rpc error");
+        assert_eq!(ClientError::from_status(502, " ", None).to_string(), "Consul returned status 502");
+
+        let limited = ClientError::from_status(429, "slow down", Some(Duration::from_secs(3)));
+        assert_eq!(limited.to_string(), "rate limited by Consul (429), retry after 3s: slow down");
+        assert!(limited.is_transient());
+        assert!(ClientError::from_status(503, "", None).is_transient());
+        assert!(!ClientError::from_status(403, "", None).is_transient());
+    }
+
+    #[test]
+    fn client_errors_drive_exit_codes() {
+        let code = |status| ConsulXError::from(ClientError::from_status(status, "", None)).exit_code();
+        assert_eq!(code(404), 1);
+        assert_eq!(code(400), 2);
+        assert_eq!(code(500), 3);
+        assert_eq!(code(403), 4);
+        assert_eq!(code(429), 7);
+        assert_eq!(ConsulXError::from(ClientError::Decode("x".into())).exit_code(), 3);
+        assert_eq!(ConsulXError::CasConflict { key: "k".into(), index: 1 }.exit_code(), 5);
+        assert_eq!(ConsulXError::TxnRolledBack { errors: vec![] }.exit_code(), 5);
+        assert!(ConsulXError::from(ClientError::from_status(403, "", None)).hint().is_some());
+    }
+}
@@ -0,0 +1,177 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::client::{ConsulXClient, KvPair};
+use crate::errors::ClientResult as Result;
+use crate::txn::{KvOp, MAX_TXN_BYTES, MAX_TXN_OPS, Txn, TxnError, TxnOutcome};
+
+/// One entry of `consul kv export` output: `{"key", "flags", "value"}` with
+/// a base64 value, so files are interchangeable with the official CLI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportEntry {
+    pub key: String,
+    #[serde(default)]
+    pub flags: u64,
+    #[serde(serialize_with = "ser_base64", deserialize_with = "de_base64")]
+    pub value: Vec<u8>,
+}
+
+fn ser_base64<S: Serializer>(v: &[u8], s: S) -> std::result::Result<S::Ok, S::Error> {
+    s.serialize_str(&BASE64.encode(v))
+}
+
+fn de_base64<'de, D: Deserializer<'de>>(de: D) -> std::result::Result<Vec<u8>, D::Error> {
+    let s = String::deserialize(de)?;
+    BASE64.decode(s.as_bytes()).map_err(serde::de::Error::custom)
+}
+
+impl From<KvPair> for ExportEntry {
+    fn from(p: KvPair) -> Self {
+        Self {
+            key: p.key,
+            flags: p.flags,
+            value: p.value.unwrap_or_default(),
+        }
+    }
+}
+
+/// How far an import got. Chunks are atomic, so a rollback means none of
+/// that chunk's keys were written; later chunks are not attempted.
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Offset of the first entry in the rolled-back chunk, and why.
+    pub rolled_back: Option<(usize, Vec<TxnError>)>,
+}
+
+/// Replace a leading `old` with `new` in every key; other keys are kept.
+pub fn rewrite_prefix(entries: &mut [ExportEntry], old: &str, new: &str) {
+    for e in entries {
+        if let Some(rest) = e.key.strip_prefix(old) {
+            e.key = format!("{new}{rest}");
+        }
+    }
+}
+
+/// Roughly what `e` adds to a txn body: the key, the base64 value and the
+/// surrounding JSON.
+fn encoded_len(e: &ExportEntry) -> usize {
+    e.key.len() + e.value.len().div_ceil(3) * 4 + 64
+}
+
+/// Split `entries` into runs that fit one transaction, by op count and by
+/// body size. An entry too big on its own still gets a chunk of its own.
+fn import_chunks(entries: &[ExportEntry]) -> Vec<&[ExportEntry]> {
+    let mut chunks = Vec::new();
+    let (mut start, mut bytes) = (0, 0);
+    for (i, e) in entries.iter().enumerate() {
+        let len = encoded_len(e);
+        if i > start && (i - start == MAX_TXN_OPS || bytes + len > MAX_TXN_BYTES) {
+            chunks.push(&entries[start..i]);
+            (start, bytes) = (i, 0);
+        }
+        bytes += len;
+    }
+    if start < entries.len() {
+        chunks.push(&entries[start..]);
+    }
+    chunks
+}
+
+impl ConsulXClient {
+    /// Every key under `prefix` in `consul kv export` form.
+    pub async fn kv_export(&self, prefix: &str) -> Result<Vec<ExportEntry>> {
+        let pairs = self.kv_get_recurse(prefix).await?;
+        Ok(pairs.into_iter().map(ExportEntry::from).collect())
+    }
+
+    /// Write `entries` in transactions of up to [`MAX_TXN_OPS`] keys and
+    /// [`MAX_TXN_BYTES`] of request body each.
+    pub async fn kv_import(&self, entries: &[ExportEntry]) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+
+        for chunk in import_chunks(entries) {
+            let mut txn = Txn::new();
+            for e in chunk {
+                txn.push(KvOp::Set {
+                    key: e.key.clone(),
+                    value: e.value.clone(),
+                    flags: e.flags,
+                });
+            }
+
+            match self.txn_commit(&txn).await? {
+                TxnOutcome::Committed(_) => report.imported += chunk.len(),
+                TxnOutcome::RolledBack(errors) => {
+                    report.rolled_back = Some((report.imported, errors));
+                    break;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_agent;
+    use crate::errors::ClientError;
+
+    #[test]
+    fn matches_consul_cli_format() {
+        let body = r#"[{"key":"app/db","flags":42,"value":"aGVsbG8="}]"#;
+        let entries: Vec<ExportEntry> = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            entries,
+            vec![ExportEntry { key: "app/db".into(), flags: 42, value: b"hello".to_vec() }]
+        );
+        assert_eq!(serde_json::to_string(&entries).unwrap(), body);
+    }
+
+    #[test]
+    fn missing_flags_default_to_zero() {
+        let e: ExportEntry = serde_json::from_str(r#"{"key":"k","value":""}"#).unwrap();
+        assert_eq!(e.flags, 0);
+        assert!(e.value.is_empty());
+    }
+
+    #[test]
+    fn rewrite_prefix_only_touches_matching_keys() {
+        let mut entries = vec![
+            ExportEntry { key: "staging/app/db".into(), flags: 0, value: vec![] },
+            ExportEntry { key: "other/x".into(), flags: 0, value: vec![] },
+        ];
+        rewrite_prefix(&mut entries, "staging/", "prod/");
+        assert_eq!(entries[0].key, "prod/app/db");
+        assert_eq!(entries[1].key, "other/x");
+    }
+
+    #[test]
+    fn chunks_split_by_key_count_and_body_size() {
+        let entry = |size: usize| ExportEntry { key: "k".into(), flags: 0, value: vec![0; size] };
+        let small: Vec<ExportEntry> = (0..130).map(|_| entry(10)).collect();
+        let lens: Vec<usize> = import_chunks(&small).iter().map(|c| c.len()).collect();
+        assert_eq!(lens, [64, 64, 2]);
+
+        let large: Vec<ExportEntry> = (0..5).map(|_| entry(200 * 1024)).collect();
+        let lens: Vec<usize> = import_chunks(&large).iter().map(|c| c.len()).collect();
+        assert_eq!(lens, [1, 1, 1, 1, 1]);
+        assert!(import_chunks(&large).iter().all(|c| c.iter().map(encoded_len).sum::<usize>() <= MAX_TXN_BYTES));
+
+        assert!(import_chunks(&[]).is_empty());
+        assert_eq!(import_chunks(&[entry(MAX_TXN_BYTES)]).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn import_errors_are_typed() {
+        let agent = test_agent::spawn(vec![(403, "Permission denied")]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+        let entries = [ExportEntry { key: "app/db".into(), flags: 0, value: b"x".to_vec() }];
+
+        let err = client.kv_import(&entries).await.unwrap_err();
+        assert!(matches!(err, ClientError::PermissionDenied { status: 403, .. }), "{err:?}");
+    }
+}
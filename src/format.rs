@@ -0,0 +1,114 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::fmt;
+use std::str::FromStr;
+
+/// A text format a value can be read from or shown as. Conversions go
+/// through a JSON document, so anything without a JSON equivalent (YAML
+/// tags, HCL expressions) is lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+    Hcl,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            "hcl" => Ok(Format::Hcl),
+            other => Err(format!("unknown format '{other}' (expected json, yaml, toml or hcl)")),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.extension())
+    }
+}
+
+impl Format {
+    /// File extension, e.g. for editor syntax highlighting.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+            Format::Hcl => "hcl",
+        }
+    }
+
+    /// Parse `text` in this format into a JSON document.
+    pub fn parse(self, text: &str) -> Result<Value> {
+        let value = match self {
+            Format::Json => serde_json::from_str(text)?,
+            Format::Yaml => serde_yaml::from_str(text)?,
+            Format::Toml => toml::from_str(text)?,
+            Format::Hcl => hcl::from_str(text)?,
+        };
+        Ok(value)
+    }
+
+    /// Render a JSON document in this format. TOML and HCL need an object
+    /// at the top level, and TOML has no null.
+    pub fn render(self, value: &Value) -> Result<String> {
+        if matches!(self, Format::Toml | Format::Hcl) && !value.is_object() {
+            anyhow::bail!("only JSON objects can be shown as {self}");
+        }
+        let mut text = match self {
+            Format::Json => serde_json::to_string_pretty(value)?,
+            Format::Yaml => serde_yaml::to_string(value)?,
+            Format::Toml => toml::to_string_pretty(value)?,
+            Format::Hcl => hcl::to_string(value)?,
+        };
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> Value {
+        json!({"name": "api", "port": 8080, "tags": ["a", "b"], "db": {"host": "db.internal"}})
+    }
+
+    #[test]
+    fn every_format_round_trips_an_object() {
+        for format in [Format::Json, Format::Yaml, Format::Toml, Format::Hcl] {
+            let text = format.render(&sample()).unwrap();
+            assert_eq!(format.parse(&text).unwrap(), sample(), "{format}:\n{text}");
+        }
+    }
+
+    #[test]
+    fn yaml_converts_to_json() {
+        let doc = Format::Yaml.parse("enabled: true\nrollout: 25\nregions:\n  - eu\n").unwrap();
+        assert_eq!(doc, json!({"enabled": true, "rollout": 25, "regions": ["eu"]}));
+    }
+
+    #[test]
+    fn scalars_and_nulls_need_a_capable_format() {
+        assert!(Format::Toml.render(&json!([1, 2])).is_err());
+        assert!(Format::Hcl.render(&json!("x")).is_err());
+        assert!(Format::Toml.render(&json!({"a": null})).is_err());
+        assert_eq!(Format::Yaml.render(&json!(3)).unwrap(), "3\n");
+    }
+
+    #[test]
+    fn names_parse_case_insensitively() {
+        assert_eq!("YML".parse::<Format>(), Ok(Format::Yaml));
+        assert!("ini".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::fmt;
+
+/// One step into a JSON document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Field(String),
+    Index(usize),
+}
+
+/// A path to a single value inside a JSON document.
+///
+/// Accepts a minimal JSONPath subset (`$`, `.field`, `['field']`, `[0]`)
+/// or an RFC 6901 JSON Pointer (`/field/0`, with `~0`/`~1` escapes).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<Self> {
+        let segments = if path.is_empty() {
+            vec![]
+        } else if path.starts_with('/') {
+            parse_pointer(path)
+        } else if path.starts_with('$') {
+            parse_dotted(path)?
+        } else {
+            anyhow::bail!("invalid path '{path}': expected `$.a.b[0]` or `/a/b/0`");
+        };
+        Ok(Self { segments })
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// True for `$` / `""`, which address the whole document.
+    pub fn is_root(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    pub fn get<'a>(&self, doc: &'a Value) -> Option<&'a Value> {
+        self.segments.iter().try_fold(doc, |cur, seg| match seg {
+            Segment::Field(name) => cur.as_object()?.get(name),
+            // A numeric pointer token can also name an object member.
+            Segment::Index(i) => match cur {
+                Value::Array(items) => items.get(*i),
+                Value::Object(map) => map.get(&i.to_string()),
+                _ => None,
+            },
+        })
+    }
+
+    /// Store `value` at this path, creating missing object members along
+    /// the way. An array index may be at most the array's length (which
+    /// appends).
+    pub fn set(&self, doc: &mut Value, value: Value) -> Result<()> {
+        let Some((last, parents)) = self.segments.split_last() else {
+            *doc = value;
+            return Ok(());
+        };
+
+        let mut cur = doc;
+        for (depth, seg) in parents.iter().enumerate() {
+            let next_is_index = matches!(self.segments[depth + 1], Segment::Index(_));
+            cur = match (seg, cur) {
+                (Segment::Field(name), cur) => {
+                    if cur.is_null() {
+                        *cur = Value::Object(Default::default());
+                    }
+                    let map = self.expect_object(cur, depth)?;
+                    map.entry(name.clone()).or_insert_with(|| {
+                        if next_is_index { Value::Array(vec![]) } else { Value::Null }
+                    })
+                }
+                (Segment::Index(i), Value::Array(items)) => {
+                    let len = items.len();
+                    items.get_mut(*i).ok_or_else(|| self.out_of_range(depth, *i, len))?
+                }
+                (Segment::Index(i), Value::Object(map)) => map.entry(i.to_string()).or_insert(Value::Null),
+                (Segment::Index(_), other) => return Err(self.type_error(depth, other)),
+            };
+        }
+
+        match (last, cur) {
+            (Segment::Field(name), cur) => {
+                if cur.is_null() {
+                    *cur = Value::Object(Default::default());
+                }
+                self.expect_object(cur, parents.len())?.insert(name.clone(), value);
+            }
+            (Segment::Index(i), Value::Array(items)) if *i < items.len() => items[*i] = value,
+            (Segment::Index(i), Value::Array(items)) if *i == items.len() => items.push(value),
+            (Segment::Index(i), Value::Array(items)) => {
+                return Err(self.out_of_range(parents.len(), *i, items.len()));
+            }
+            (Segment::Index(i), Value::Object(map)) => {
+                map.insert(i.to_string(), value);
+            }
+            (Segment::Index(_), other) => return Err(self.type_error(parents.len(), other)),
+        }
+        Ok(())
+    }
+
+    /// Remove and return the value at this path; `None` if it wasn't there.
+    pub fn remove(&self, doc: &mut Value) -> Result<Option<Value>> {
+        let Some((last, parents)) = self.segments.split_last() else {
+            anyhow::bail!("cannot remove the document root");
+        };
+        let parent = JsonPath { segments: parents.to_vec() };
+        let Some(cur) = parent.get_mut(doc) else {
+            return Ok(None);
+        };
+
+        Ok(match (last, cur) {
+            (Segment::Field(name), Value::Object(map)) => map.remove(name),
+            (Segment::Index(i), Value::Object(map)) => map.remove(&i.to_string()),
+            (Segment::Index(i), Value::Array(items)) if *i < items.len() => Some(items.remove(*i)),
+            _ => None,
+        })
+    }
+
+    fn get_mut<'a>(&self, doc: &'a mut Value) -> Option<&'a mut Value> {
+        self.segments.iter().try_fold(doc, |cur, seg| match seg {
+            Segment::Field(name) => cur.as_object_mut()?.get_mut(name),
+            Segment::Index(i) => match cur {
+                Value::Array(items) => items.get_mut(*i),
+                Value::Object(map) => map.get_mut(&i.to_string()),
+                _ => None,
+            },
+        })
+    }
+
+    /// The path up to (not including) segment `depth`, for error messages.
+    fn prefix(&self, depth: usize) -> JsonPath {
+        JsonPath { segments: self.segments[..depth].to_vec() }
+    }
+
+    fn expect_object<'a>(&self, v: &'a mut Value, depth: usize) -> Result<&'a mut serde_json::Map<String, Value>> {
+        let err = self.type_error(depth, v);
+        v.as_object_mut().ok_or(err)
+    }
+
+    fn type_error(&self, depth: usize, found: &Value) -> anyhow::Error {
+        let kind = match found {
+            Value::Null => "null",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+            Value::Array(_) => "an array",
+            Value::Object(_) => "an object",
+        };
+        anyhow::anyhow!("cannot descend into {}: it is {kind}", self.prefix(depth))
+    }
+
+    fn out_of_range(&self, depth: usize, index: usize, len: usize) -> anyhow::Error {
+        anyhow::anyhow!("index {index} is out of range for {} (length {len})", self.prefix(depth))
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for seg in &self.segments {
+            match seg {
+                Segment::Field(name) if is_identifier(name) => write!(f, ".{name}")?,
+                Segment::Field(name) => write!(f, "['{}']", name.replace('\'', "\\'"))?,
+                Segment::Index(i) => write!(f, "[{i}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn parse_pointer(path: &str) -> Vec<Segment> {
+    path[1..]
+        .split('/')
+        .map(|token| {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            match token.parse::<usize>() {
+                Ok(i) if !token.starts_with('+') && (token == "0" || !token.starts_with('0')) => {
+                    Segment::Index(i)
+                }
+                _ => Segment::Field(token),
+            }
+        })
+        .collect()
+}
+
+fn parse_dotted(path: &str) -> Result<Vec<Segment>> {
+    let bad = |why: &str| anyhow::anyhow!("invalid path '{path}': {why}");
+    let chars: Vec<char> = path.chars().collect();
+    let mut segments = Vec::new();
+    let mut i = 1; // skip `$`
+
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                let start = i + 1;
+                let mut end = start;
+                while end < chars.len() && chars[end] != '.' && chars[end] != '[' {
+                    end += 1;
+                }
+                if end == start {
+                    return Err(bad("empty field name"));
+                }
+                segments.push(Segment::Field(chars[start..end].iter().collect()));
+                i = end;
+            }
+            '[' => {
+                let close = chars[i..]
+                    .iter()
+                    .position(|c| *c == ']')
+                    .map(|n| i + n)
+                    .ok_or_else(|| bad("unclosed '['"))?;
+                let inner: String = chars[i + 1..close].iter().collect();
+                let quoted = inner.len() >= 2
+                    && (inner.starts_with('\'') && inner.ends_with('\'')
+                        || inner.starts_with('"') && inner.ends_with('"'));
+                if quoted {
+                    segments.push(Segment::Field(inner[1..inner.len() - 1].to_string()));
+                } else {
+                    let index = inner
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| bad("expected an index or a quoted name inside []"))?;
+                    segments.push(Segment::Index(index));
+                }
+                i = close + 1;
+            }
+            c => return Err(bad(&format!("unexpected '{c}'"))),
+        }
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field(s: &str) -> Segment {
+        Segment::Field(s.into())
+    }
+
+    #[test]
+    fn parses_dotted_paths() {
+        let p = JsonPath::parse("$.db.hosts[1]['read-only']").unwrap();
+        assert_eq!(
+            p.segments(),
+            &[field("db"), field("hosts"), Segment::Index(1), field("read-only")]
+        );
+        assert!(JsonPath::parse("$").unwrap().is_root());
+    }
+
+    #[test]
+    fn parses_json_pointers() {
+        let p = JsonPath::parse("/db/hosts/0/a~1b").unwrap();
+        assert_eq!(
+            p.segments(),
+            &[field("db"), field("hosts"), Segment::Index(0), field("a/b")]
+        );
+        // leading zeros are names, not indexes
+        assert_eq!(JsonPath::parse("/01").unwrap().segments(), &[field("01")]);
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        for bad in ["enabled", "$.", "$.a[", "$.a[x]", "$a"] {
+            assert!(JsonPath::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn get_walks_objects_and_arrays() {
+        let doc = json!({"db": {"hosts": ["a", "b"], "enabled": true}, "7": "seven"});
+        let get = |p: &str| JsonPath::parse(p).unwrap().get(&doc).cloned();
+        assert_eq!(get("$.db.enabled"), Some(json!(true)));
+        assert_eq!(get("/db/hosts/1"), Some(json!("b")));
+        assert_eq!(get("/7"), Some(json!("seven")));
+        assert_eq!(get("$.db.missing"), None);
+        assert_eq!(get("$.db.hosts[5]"), None);
+        assert_eq!(get("$"), Some(doc.clone()));
+    }
+
+    #[test]
+    fn set_creates_missing_members() {
+        let mut doc = json!({"db": {"host": "a"}});
+        JsonPath::parse("$.db.port").unwrap().set(&mut doc, json!(5432)).unwrap();
+        JsonPath::parse("$.cache.ttl").unwrap().set(&mut doc, json!("30s")).unwrap();
+        JsonPath::parse("$.tags[0]").unwrap().set(&mut doc, json!("x")).unwrap();
+        assert_eq!(
+            doc,
+            json!({"db": {"host": "a", "port": 5432}, "cache": {"ttl": "30s"}, "tags": ["x"]})
+        );
+    }
+
+    #[test]
+    fn set_replaces_and_appends_array_items() {
+        let mut doc = json!({"hosts": ["a", "b"]});
+        JsonPath::parse("/hosts/0").unwrap().set(&mut doc, json!("z")).unwrap();
+        JsonPath::parse("/hosts/2").unwrap().set(&mut doc, json!("c")).unwrap();
+        assert_eq!(doc, json!({"hosts": ["z", "b", "c"]}));
+
+        let err = JsonPath::parse("/hosts/9").unwrap().set(&mut doc, json!(1)).unwrap_err();
+        assert!(err.to_string().contains("out of range for $.hosts"), "{err}");
+    }
+
+    #[test]
+    fn set_through_a_scalar_is_an_error() {
+        let mut doc = json!({"enabled": true});
+        let err = JsonPath::parse("$.enabled.x").unwrap().set(&mut doc, json!(1)).unwrap_err();
+        assert_eq!(err.to_string(), "cannot descend into $.enabled: it is a boolean");
+    }
+
+    #[test]
+    fn remove_returns_the_old_value() {
+        let mut doc = json!({"a": {"b": 1, "c": 2}, "l": [1, 2, 3]});
+        assert_eq!(JsonPath::parse("$.a.b").unwrap().remove(&mut doc).unwrap(), Some(json!(1)));
+        assert_eq!(JsonPath::parse("$.l[1]").unwrap().remove(&mut doc).unwrap(), Some(json!(2)));
+        assert_eq!(JsonPath::parse("$.x.y").unwrap().remove(&mut doc).unwrap(), None);
+        assert_eq!(doc, json!({"a": {"c": 2}, "l": [1, 3]}));
+        assert!(JsonPath::parse("$").unwrap().remove(&mut doc).is_err());
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for path in ["$", "$.db.hosts[0]", "$['a.b'].c"] {
+            assert_eq!(JsonPath::parse(path).unwrap().to_string(), path);
+        }
+    }
+}
@@ -1,8 +1,39 @@
+pub mod backup;
+pub mod batch;
+pub mod builder;
+pub mod cli;
 pub mod client;
+pub mod endpoint;
 pub mod repl;
+pub mod schema;
 pub mod parser;
 pub mod commands;
 pub mod errors;
+pub mod export;
+pub mod format;
+pub mod jsonpath;
+pub mod txn;
+pub mod session;
+pub mod lock;
+pub mod election;
+pub mod transfer;
+pub mod update;
+pub mod retry;
+pub mod tls;
 
-pub use client::ConsulXClient;
+pub use backup::{Backup, RestorePlan};
+pub use builder::ConsulXClientBuilder;
+pub use client::{CasOutcome, ConsulXClient, JsonListing, KvPair};
+pub use election::LeaderElection;
+pub use errors::{ClientError, ClientResult, UpdateError};
+pub use export::{ExportEntry, ImportReport};
+pub use format::Format;
+pub use jsonpath::JsonPath;
+pub use lock::{ConsulLock, LockOptions};
 pub use repl::start_repl;
+pub use retry::{Idempotency, RetryPolicy};
+pub use schema::SchemaRegistry;
+pub use session::{SessionEntry, SessionOptions};
+pub use tls::TlsOptions;
+pub use transfer::{TransferMode, TransferReport};
+pub use txn::{Txn, TxnOutcome};
@@ -0,0 +1,173 @@
+use anyhow::Result;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::client::ConsulXClient;
+use crate::errors::ClientResult;
+use crate::session::{SessionBehavior, SessionOptions, check_ttl};
+
+/// Settings for the session backing a [`ConsulLock`].
+#[derive(Debug, Clone)]
+pub struct LockOptions {
+    /// Session TTL, 10s to 24h; the lock renews at half this interval.
+    pub ttl: Duration,
+    /// Value written to the key while the lock is held.
+    pub value: Vec<u8>,
+    pub session_name: String,
+}
+
+impl Default for LockOptions {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(15),
+            value: Vec::new(),
+            session_name: "consulx-lock".into(),
+        }
+    }
+}
+
+/// A held KV lock. The backing session is renewed in the background for as
+/// long as the guard lives; dropping it releases the key and destroys the
+/// session (prefer [`release`](Self::release) to observe errors).
+pub struct ConsulLock {
+    client: ConsulXClient,
+    key: String,
+    value: Vec<u8>,
+    session: String,
+    held: Arc<AtomicBool>,
+    renewer: JoinHandle<()>,
+    released: bool,
+}
+
+impl LockOptions {
+    /// Check the TTL is one Consul accepts for a session.
+    pub fn validate(&self) -> ClientResult<()> {
+        check_ttl(self.ttl)
+    }
+}
+
+impl ConsulLock {
+    /// Try once to take the lock; `None` if another session holds it.
+    pub async fn try_acquire(client: &ConsulXClient, key: &str, opts: LockOptions) -> Result<Option<Self>> {
+        opts.validate()?;
+        let session = client
+            .session_create(&SessionOptions {
+                name: Some(opts.session_name.clone()),
+                ttl: Some(opts.ttl),
+                behavior: SessionBehavior::Release,
+                ..Default::default()
+            })
+            .await?;
+
+        match client.kv_acquire(key, &opts.value, &session).await {
+            Ok(true) => Ok(Some(Self::held(client, key, opts, session))),
+            Ok(false) => {
+                client.session_destroy(&session).await.ok();
+                Ok(None)
+            }
+            Err(e) => {
+                client.session_destroy(&session).await.ok();
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Wait until the lock can be taken, using blocking queries on the key
+    /// to avoid polling while another holder has it.
+    pub async fn acquire(client: &ConsulXClient, key: &str, opts: LockOptions) -> Result<Self> {
+        let mut index: Option<u64> = None;
+        loop {
+            if let Some(lock) = Self::try_acquire(client, key, opts.clone()).await? {
+                return Ok(lock);
+            }
+            let (new_index, _) = client.kv_watch(key, index).await?;
+            index = Some(new_index);
+            if new_index == 0 {
+                tokio::time::sleep(Duration::from_secs(2)).await;
+            }
+        }
+    }
+
+    fn held(client: &ConsulXClient, key: &str, opts: LockOptions, session: String) -> Self {
+        let held = Arc::new(AtomicBool::new(true));
+        let renewer = tokio::spawn(renew_loop(
+            client.clone(),
+            session.clone(),
+            opts.ttl / 2,
+            held.clone(),
+        ));
+
+        Self {
+            client: client.clone(),
+            key: key.to_string(),
+            value: opts.value,
+            session,
+            held,
+            renewer,
+            released: false,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session
+    }
+
+    /// `false` once the session could not be renewed, i.e. the lock is lost.
+    pub fn is_held(&self) -> bool {
+        self.held.load(Ordering::SeqCst)
+    }
+
+    /// Release the key and destroy the session.
+    pub async fn release(mut self) -> Result<()> {
+        self.released = true;
+        self.renewer.abort();
+        self.held.store(false, Ordering::SeqCst);
+
+        let released = self.client.kv_release(&self.key, &self.value, &self.session).await;
+        self.client.session_destroy(&self.session).await?;
+        released?;
+        Ok(())
+    }
+}
+
+impl Drop for ConsulLock {
+    fn drop(&mut self) {
+        self.renewer.abort();
+        if self.released {
+            return;
+        }
+
+        // Best effort: Drop can't await, so hand the cleanup to the runtime.
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            let client = self.client.clone();
+            let key = std::mem::take(&mut self.key);
+            let value = std::mem::take(&mut self.value);
+            let session = std::mem::take(&mut self.session);
+            handle.spawn(async move {
+                client.kv_release(&key, &value, &session).await.ok();
+                client.session_destroy(&session).await.ok();
+            });
+        }
+    }
+}
+
+/// Keep a session alive until it is reported gone. Transient errors are
+/// retried on the next tick; the TTL leaves room for a missed renewal.
+pub(crate) async fn renew_loop(client: ConsulXClient, session: String, every: Duration, held: Arc<AtomicBool>) {
+    let mut tick = tokio::time::interval(every);
+    tick.tick().await; // first tick fires immediately
+
+    loop {
+        tick.tick().await;
+        if let Ok(None) = client.session_renew(&session).await {
+            held.store(false, Ordering::SeqCst);
+            return;
+        }
+    }
+}
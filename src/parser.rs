@@ -1,17 +1,52 @@
 use crate::errors::{Result, ConsulXError};
+use crate::format::Format;
+
+/// What `diff` compares.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffTarget {
+    /// Two prefixes, or a prefix and an export file (`b` names an existing file).
+    Prefixes { a: String, b: String },
+    /// The same prefix in two datacenters.
+    Datacenters { prefix: String, a: String, b: String },
+}
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Command {
-    Get { key: String },
-    Put { key: String, value: String },
-    Delete { key: String },
+    Get { key: String, as_format: Option<Format> },
+    Stat { key: String },
+    /// With `from`, the value is parsed in that format and stored as JSON,
+    /// or as given when `verbatim`.
+    Put { key: String, value: String, cas: Option<u64>, from: Option<Format>, verbatim: bool },
+    Delete { key: String, cas: Option<u64> },
+    DeleteTree { prefix: String, yes: bool },
     List { prefix: String },
     Watch { key: String },
     WatchPrefix { prefix: String },
     Tree { prefix: String },
-    GetJson { key: String },
+    GetJson { key: String, path: Option<String> },
+    SetJson { key: String, path: String, json: String },
+    UnsetJson { key: String, path: String },
+    /// RFC 7386 merge patch, or an RFC 6902 operation list with `json_patch`.
+    Patch { key: String, patch: String, json_patch: bool },
     PutJson { key: String, json: String },
-    Edit { key: String },
+    Edit { key: String, as_format: Option<Format> },
+    Export { prefix: String, file: Option<String> },
+    Import { file: String, rewrite: Option<(String, String)> },
+    Backup { prefix: String, file: String },
+    Restore { file: String, yes: bool },
+    Diff { target: DiffTarget },
+    Push { dir: String, prefix: String, prune: bool, yes: bool },
+    Pull { prefix: String, dir: String, prune: bool, yes: bool },
+    Grep { pattern: String, prefix: String, ignore_case: bool },
+    Find { prefix: String, key_regex: Option<String>, json_path: Option<String>, eq: Option<String> },
+    Copy { src: String, dst: String, recursive: bool },
+    Move { src: String, dst: String, recursive: bool },
+    Begin,
+    Commit,
+    Abort,
+    Lock { key: String },
+    Unlock { key: String },
+    Sessions,
     Help,
     Empty,
 }
@@ -38,6 +73,119 @@ fn split3(input: &str) -> (String, Option<&str>, Option<&str>) {
     (cmd, arg, rest)
 }
 
+/// Split off the first whitespace-delimited token, keeping the remainder
+/// verbatim (leading whitespace trimmed).
+fn split_first(input: &str) -> (Option<&str>, Option<&str>) {
+    let mut it = input.splitn(2, char::is_whitespace);
+    let first = it.next().filter(|s| !s.is_empty());
+    let rest = it.next().map(str::trim_start).filter(|s| !s.is_empty());
+    (first, rest)
+}
+
+/// Split `input` on whitespace, treating '...' and "..." as one word with
+/// the quotes removed, so patterns and values may contain spaces.
+fn words(input: &str) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+
+    for c in input.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => cur.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_word = true;
+            }
+            None if c.is_whitespace() => {
+                if in_word {
+                    out.push(std::mem::take(&mut cur));
+                    in_word = false;
+                }
+            }
+            None => {
+                cur.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(ConsulXError::InvalidArgument("unterminated quote".into()));
+    }
+    if in_word {
+        out.push(cur);
+    }
+    Ok(out)
+}
+
+/// Peel an optional leading `--cas <index>` off the argument string.
+fn take_cas(args: &str) -> Result<(Option<u64>, &str)> {
+    let (flag, rest) = split_first(args);
+    if flag != Some("--cas") {
+        return Ok((None, args));
+    }
+
+    let (index, rest) = split_first(rest.unwrap_or(""));
+    let index = index.ok_or(ConsulXError::MissingArgument("index"))?;
+    let index = index.parse::<u64>().map_err(|_| {
+        ConsulXError::InvalidArgument(format!("--cas expects a numeric index, got '{index}'"))
+    })?;
+    Ok((Some(index), rest.unwrap_or("")))
+}
+
+fn parse_format(name: Option<&str>) -> Result<Format> {
+    name.ok_or(ConsulXError::MissingArgument("format"))?
+        .parse()
+        .map_err(ConsulXError::InvalidArgument)
+}
+
+/// Peel an optional leading `--as <format>` off the word iterator's front.
+fn take_as<'a>(p: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>) -> Result<Option<Format>> {
+    if p.next_if_eq(&"--as").is_none() {
+        return Ok(None);
+    }
+    parse_format(p.next()).map(Some)
+}
+
+/// Split arguments into recognised flags and positional words. Unknown
+/// `-`-prefixed words are rejected so a typo doesn't become a key name.
+fn flags_and_args<'a>(
+    args: impl Iterator<Item = &'a str>,
+    known: &[&str],
+) -> Result<(Vec<&'a str>, Vec<&'a str>)> {
+    let mut flags = Vec::new();
+    let mut positional = Vec::new();
+    for arg in args {
+        if known.contains(&arg) {
+            flags.push(arg);
+        } else if arg.starts_with('-') && arg.len() > 1 {
+            return Err(ConsulXError::InvalidArgument(format!("unknown flag '{arg}'")));
+        } else {
+            positional.push(arg);
+        }
+    }
+    Ok((flags, positional))
+}
+
+/// Parse `<a> <b> [--prune] [--yes|-y]` for push/pull.
+fn sync_args<'a>(args: impl Iterator<Item = &'a str>, first: &'static str, second: &'static str) -> Result<(String, String, bool, bool)> {
+    let (flags, pos) = flags_and_args(args, &["--prune", "--yes", "-y"])?;
+    let a = pos.first().ok_or(ConsulXError::MissingArgument(first))?;
+    let b = pos.get(1).ok_or(ConsulXError::MissingArgument(second))?;
+    let prune = flags.contains(&"--prune");
+    let yes = flags.iter().any(|f| *f == "--yes" || *f == "-y");
+    Ok((a.to_string(), b.to_string(), prune, yes))
+}
+
+/// Parse `[-r|--recursive] <src> <dst>` for cp/mv.
+fn transfer_args<'a>(args: impl Iterator<Item = &'a str>) -> Result<(String, String, bool)> {
+    let (flags, pos) = flags_and_args(args, &["-r", "--recursive"])?;
+    let src = pos.first().ok_or(ConsulXError::MissingArgument("src"))?;
+    let dst = pos.get(1).ok_or(ConsulXError::MissingArgument("dst"))?;
+    Ok((src.to_string(), dst.to_string(), !flags.is_empty()))
+}
+
 pub fn parse(input: &str) -> Result<Command> {
     let trimmed = input.trim();
 
@@ -45,31 +193,83 @@ pub fn parse(input: &str) -> Result<Command> {
         return Ok(Command::Empty);
     }
 
-    let mut p = trimmed.split_whitespace();
+    let mut p = trimmed.split_whitespace().peekable();
     let cmd = p.next().unwrap().to_lowercase();
 
     match cmd.as_str() {
-        "get" => Ok(Command::Get {
+        "get" => {
+            if p.next_if_eq(&"-v").is_some() {
+                return Ok(Command::Stat {
+                    key: p
+                        .next()
+                        .ok_or(ConsulXError::MissingArgument("key"))?
+                        .into(),
+                });
+            }
+            let as_format = take_as(&mut p)?;
+            let key = p.next().ok_or(ConsulXError::MissingArgument("key"))?;
+            Ok(Command::Get { key: key.into(), as_format })
+        }
+        "stat" => Ok(Command::Stat {
             key: p
                 .next()
                 .ok_or(ConsulXError::MissingArgument("key"))?
                 .into(),
         }),
         "put" => {
-            let (_, key, rest) = split3(trimmed);
+            let (_, args) = split_first(trimmed);
+            let mut args = args.unwrap_or("");
+            let (mut cas, mut from, mut verbatim) = (None, None, false);
+            // leading flags, in any order; anything after the key is the value
+            loop {
+                let (flag, rest) = split_first(args);
+                match flag {
+                    Some("--cas") => (cas, args) = take_cas(args)?,
+                    Some("--from") => {
+                        let (name, rest) = split_first(rest.unwrap_or(""));
+                        from = Some(parse_format(name)?);
+                        args = rest.unwrap_or("");
+                    }
+                    Some("--verbatim") => {
+                        verbatim = true;
+                        args = rest.unwrap_or("");
+                    }
+                    _ => break,
+                }
+            }
+            if verbatim && from.is_none() {
+                return Err(ConsulXError::InvalidArgument("--verbatim needs --from <format>".into()));
+            }
+
+            let (key, rest) = split_first(args);
             let key = key.ok_or(ConsulXError::MissingArgument("key"))?;
             let value = rest.ok_or(ConsulXError::MissingArgument("value"))?;
             Ok(Command::Put {
                 key: key.into(),
                 value: unquote(value).to_string(),
+                cas,
+                from,
+                verbatim,
+            })
+        }
+        "del" | "delete" => {
+            let (_, args) = split_first(trimmed);
+            let args = args.unwrap_or("");
+            if args.split_whitespace().any(|a| a == "-r" || a == "--recursive") {
+                let (flags, pos) = flags_and_args(args.split_whitespace(), &["-r", "--recursive", "--yes", "-y"])?;
+                let prefix = pos.first().map(|p| unquote(p)).filter(|p| !p.trim_matches('/').is_empty());
+                return Ok(Command::DeleteTree {
+                    prefix: prefix.ok_or(ConsulXError::MissingArgument("prefix"))?.into(),
+                    yes: flags.iter().any(|f| *f == "--yes" || *f == "-y"),
+                });
+            }
+            let (cas, args) = take_cas(args)?;
+            let (key, _) = split_first(args);
+            Ok(Command::Delete {
+                key: key.ok_or(ConsulXError::MissingArgument("key"))?.into(),
+                cas,
             })
         }
-        "del" | "delete" => Ok(Command::Delete {
-            key: p
-                .next()
-                .ok_or(ConsulXError::MissingArgument("key"))?
-                .into(),
-        }),
         "list" => Ok(Command::List {
             prefix: p.next().unwrap_or("").into(),
         }),
@@ -93,7 +293,39 @@ pub fn parse(input: &str) -> Result<Command> {
                 .next()
                 .ok_or(ConsulXError::MissingArgument("key"))?
                 .into(),
+            path: p.next().map(|path| unquote(path).to_string()),
         }),
+        "set-json" => {
+            let (_, key, rest) = split3(trimmed);
+            let key = key.ok_or(ConsulXError::MissingArgument("key"))?;
+            let (path, json) = split_first(rest.unwrap_or(""));
+            let path = path.ok_or(ConsulXError::MissingArgument("path"))?;
+            let json = json.ok_or(ConsulXError::MissingArgument("json"))?;
+            Ok(Command::SetJson {
+                key: key.into(),
+                path: unquote(path).to_string(),
+                json: json.to_string(),
+            })
+        }
+        "patch" => {
+            let (_, args) = split_first(trimmed);
+            let (first, rest) = split_first(args.unwrap_or(""));
+            let json_patch = first == Some("--json-patch");
+            let (key, patch) = if json_patch { split_first(rest.unwrap_or("")) } else { (first, rest) };
+            Ok(Command::Patch {
+                key: key.ok_or(ConsulXError::MissingArgument("key"))?.into(),
+                patch: patch.ok_or(ConsulXError::MissingArgument("patch"))?.into(),
+                json_patch,
+            })
+        }
+        "unset-json" => {
+            let key = p.next().ok_or(ConsulXError::MissingArgument("key"))?;
+            let path = p.next().ok_or(ConsulXError::MissingArgument("path"))?;
+            Ok(Command::UnsetJson {
+                key: key.into(),
+                path: unquote(path).to_string(),
+            })
+        }
         "put-json" => {
             let (_, key, rest) = split3(trimmed);
             let key = key.ok_or(ConsulXError::MissingArgument("key"))?;
@@ -103,12 +335,149 @@ pub fn parse(input: &str) -> Result<Command> {
                 json: json.to_string(),
             })
         }
-        "edit" => Ok(Command::Edit {
+        "edit" => {
+            let as_format = take_as(&mut p)?;
+            let key = p.next().ok_or(ConsulXError::MissingArgument("key"))?;
+            Ok(Command::Edit { key: key.into(), as_format })
+        }
+        "export" => Ok(Command::Export {
+            prefix: p.next().unwrap_or("").into(),
+            file: p.next().map(String::from),
+        }),
+        "import" => {
+            let mut file = None;
+            let mut rewrite = None;
+            while let Some(arg) = p.next() {
+                if arg == "--prefix-rewrite" {
+                    let spec = p.next().ok_or(ConsulXError::MissingArgument("old=new"))?;
+                    let (old, new) = spec.split_once('=').ok_or_else(|| {
+                        ConsulXError::InvalidArgument(format!("--prefix-rewrite expects old=new, got '{spec}'"))
+                    })?;
+                    rewrite = Some((old.to_string(), new.to_string()));
+                } else {
+                    file = Some(arg.to_string());
+                }
+            }
+            Ok(Command::Import {
+                file: file.ok_or(ConsulXError::MissingArgument("file"))?,
+                rewrite,
+            })
+        }
+        "backup" => Ok(Command::Backup {
+            prefix: p
+                .next()
+                .ok_or(ConsulXError::MissingArgument("prefix"))?
+                .into(),
+            file: p
+                .next()
+                .ok_or(ConsulXError::MissingArgument("file"))?
+                .into(),
+        }),
+        "restore" => {
+            let (flags, pos) = flags_and_args(p, &["--yes", "-y"])?;
+            Ok(Command::Restore {
+                file: pos.first().ok_or(ConsulXError::MissingArgument("file"))?.to_string(),
+                yes: !flags.is_empty(),
+            })
+        }
+        "diff" => {
+            let mut dcs = Vec::new();
+            let mut pos = Vec::new();
+            while let Some(arg) = p.next() {
+                if arg == "--dc" {
+                    dcs.push(p.next().ok_or(ConsulXError::MissingArgument("dc"))?.to_string());
+                } else {
+                    pos.push(arg.to_string());
+                }
+            }
+
+            let target = match (dcs.len(), pos.len()) {
+                (0, 2) => {
+                    let b = pos.pop().unwrap();
+                    DiffTarget::Prefixes { a: pos.pop().unwrap(), b }
+                }
+                (2, 1) => {
+                    let b = dcs.pop().unwrap();
+                    DiffTarget::Datacenters { prefix: pos.pop().unwrap(), a: dcs.pop().unwrap(), b }
+                }
+                (0, 0) | (2, 0) => return Err(ConsulXError::MissingArgument("prefix")),
+                (0, 1) => return Err(ConsulXError::MissingArgument("second prefix or file")),
+                _ => {
+                    return Err(ConsulXError::InvalidArgument(
+                        "use `diff <a> <b>` or `diff --dc <dc1> --dc <dc2> <prefix>`".into(),
+                    ));
+                }
+            };
+            Ok(Command::Diff { target })
+        }
+        "push" => {
+            let (dir, prefix, prune, yes) = sync_args(p, "dir", "prefix")?;
+            Ok(Command::Push { dir, prefix, prune, yes })
+        }
+        "pull" => {
+            let (prefix, dir, prune, yes) = sync_args(p, "prefix", "dir")?;
+            Ok(Command::Pull { prefix, dir, prune, yes })
+        }
+        "grep" => {
+            let (_, args) = split_first(trimmed);
+            let args = words(args.unwrap_or(""))?;
+            let (flags, pos) = flags_and_args(args.iter().map(String::as_str), &["-i"])?;
+            Ok(Command::Grep {
+                pattern: pos.first().ok_or(ConsulXError::MissingArgument("pattern"))?.to_string(),
+                prefix: pos.get(1).unwrap_or(&"").to_string(),
+                ignore_case: !flags.is_empty(),
+            })
+        }
+        "find" => {
+            let (_, args) = split_first(trimmed);
+            let mut args = words(args.unwrap_or(""))?.into_iter();
+            let (mut prefix, mut key_regex, mut json_path, mut eq) = (None, None, None, None);
+            while let Some(arg) = args.next() {
+                let slot = match arg.as_str() {
+                    "--key-regex" => &mut key_regex,
+                    "--json-path" => &mut json_path,
+                    "--eq" => &mut eq,
+                    flag if flag.starts_with("--") => {
+                        return Err(ConsulXError::InvalidArgument(format!("unknown flag '{flag}'")));
+                    }
+                    _ => {
+                        prefix = Some(arg);
+                        continue;
+                    }
+                };
+                *slot = Some(args.next().ok_or(ConsulXError::MissingArgument("flag value"))?);
+            }
+            Ok(Command::Find {
+                prefix: prefix.unwrap_or_default(),
+                key_regex,
+                json_path,
+                eq,
+            })
+        }
+        "cp" | "copy" => {
+            let (src, dst, recursive) = transfer_args(p)?;
+            Ok(Command::Copy { src, dst, recursive })
+        }
+        "mv" | "move" | "rename" => {
+            let (src, dst, recursive) = transfer_args(p)?;
+            Ok(Command::Move { src, dst, recursive })
+        }
+        "begin" => Ok(Command::Begin),
+        "commit" => Ok(Command::Commit),
+        "abort" => Ok(Command::Abort),
+        "lock" => Ok(Command::Lock {
+            key: p
+                .next()
+                .ok_or(ConsulXError::MissingArgument("key"))?
+                .into(),
+        }),
+        "unlock" => Ok(Command::Unlock {
             key: p
                 .next()
                 .ok_or(ConsulXError::MissingArgument("key"))?
                 .into(),
         }),
+        "sessions" => Ok(Command::Sessions),
         "help" | "?" => Ok(Command::Help),
         "exit" | "quit" => Ok(Command::Empty),
         other => Err(ConsulXError::UnknownCommand(other.to_string())),
@@ -130,7 +499,7 @@ mod tests {
     fn get_parses_key() {
         assert_eq!(
             parse("get app/db").unwrap(),
-            Command::Get { key: "app/db".into() }
+            Command::Get { key: "app/db".into(), as_format: None }
         );
     }
 
@@ -138,17 +507,17 @@ mod tests {
     fn command_word_is_case_insensitive() {
         assert_eq!(
             parse("GET app/db").unwrap(),
-            Command::Get { key: "app/db".into() }
+            Command::Get { key: "app/db".into(), as_format: None }
         );
         assert_eq!(
             parse("Put k v").unwrap(),
-            Command::Put { key: "k".into(), value: "v".into() }
+            Command::Put { key: "k".into(), value: "v".into(), cas: None, from: None, verbatim: false }
         );
     }
 
     #[test]
     fn del_and_delete_are_aliases() {
-        let expected = Command::Delete { key: "k".into() };
+        let expected = Command::Delete { key: "k".into(), cas: None };
         assert_eq!(parse("del k").unwrap(), expected);
         assert_eq!(parse("delete k").unwrap(), expected);
     }
@@ -159,7 +528,7 @@ mod tests {
         // double space into a single one.
         assert_eq!(
             parse("put k hello   world").unwrap(),
-            Command::Put { key: "k".into(), value: "hello   world".into() }
+            Command::Put { key: "k".into(), value: "hello   world".into(), cas: None, from: None, verbatim: false }
         );
     }
 
@@ -167,11 +536,11 @@ mod tests {
     fn put_strips_one_pair_of_surrounding_quotes() {
         assert_eq!(
             parse(r#"put k "a  b""#).unwrap(),
-            Command::Put { key: "k".into(), value: "a  b".into() }
+            Command::Put { key: "k".into(), value: "a  b".into(), cas: None, from: None, verbatim: false }
         );
         assert_eq!(
             parse("put k 'single'").unwrap(),
-            Command::Put { key: "k".into(), value: "single".into() }
+            Command::Put { key: "k".into(), value: "single".into(), cas: None, from: None, verbatim: false }
         );
     }
 
@@ -179,11 +548,11 @@ mod tests {
     fn put_keeps_unbalanced_or_inner_quotes() {
         assert_eq!(
             parse(r#"put k "unclosed"#).unwrap(),
-            Command::Put { key: "k".into(), value: r#""unclosed"#.into() }
+            Command::Put { key: "k".into(), value: r#""unclosed"#.into(), cas: None, from: None, verbatim: false }
         );
         assert_eq!(
             parse(r#"put k say "hi""#).unwrap(),
-            Command::Put { key: "k".into(), value: r#"say "hi""#.into() }
+            Command::Put { key: "k".into(), value: r#"say "hi""#.into(), cas: None, from: None, verbatim: false }
         );
     }
 
@@ -255,4 +624,318 @@ mod tests {
         assert_eq!(unquote("\""), "\""); // single char, not a pair
         assert_eq!(unquote(""), "");
     }
+
+    #[test]
+    fn stat_and_verbose_get() {
+        let expected = Command::Stat { key: "app/db".into() };
+        assert_eq!(parse("stat app/db").unwrap(), expected);
+        assert_eq!(parse("get -v app/db").unwrap(), expected);
+        assert!(matches!(
+            parse("get -v"),
+            Err(ConsulXError::MissingArgument("key"))
+        ));
+    }
+
+    #[test]
+    fn put_with_cas_index() {
+        assert_eq!(
+            parse("put --cas 42 k hello  world").unwrap(),
+            Command::Put { key: "k".into(), value: "hello  world".into(), cas: Some(42), from: None, verbatim: false }
+        );
+        // a flag-looking value after the key is just part of the value
+        assert_eq!(
+            parse("put k --cas 42").unwrap(),
+            Command::Put { key: "k".into(), value: "--cas 42".into(), cas: None, from: None, verbatim: false }
+        );
+    }
+
+    #[test]
+    fn del_with_cas_index() {
+        assert_eq!(
+            parse("del --cas 7 k").unwrap(),
+            Command::Delete { key: "k".into(), cas: Some(7) }
+        );
+        assert!(matches!(
+            parse("del --cas 7"),
+            Err(ConsulXError::MissingArgument("key"))
+        ));
+    }
+
+    #[test]
+    fn cas_index_must_be_numeric() {
+        assert!(matches!(
+            parse("put --cas abc k v"),
+            Err(ConsulXError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            parse("del --cas"),
+            Err(ConsulXError::MissingArgument("index"))
+        ));
+    }
+
+    #[test]
+    fn export_prefix_and_optional_file() {
+        assert_eq!(
+            parse("export app/").unwrap(),
+            Command::Export { prefix: "app/".into(), file: None }
+        );
+        assert_eq!(
+            parse("export app/ out.json").unwrap(),
+            Command::Export { prefix: "app/".into(), file: Some("out.json".into()) }
+        );
+    }
+
+    #[test]
+    fn import_with_prefix_rewrite() {
+        assert_eq!(
+            parse("import in.json").unwrap(),
+            Command::Import { file: "in.json".into(), rewrite: None }
+        );
+        assert_eq!(
+            parse("import in.json --prefix-rewrite staging/=prod/").unwrap(),
+            Command::Import {
+                file: "in.json".into(),
+                rewrite: Some(("staging/".into(), "prod/".into())),
+            }
+        );
+        assert!(matches!(
+            parse("import in.json --prefix-rewrite staging"),
+            Err(ConsulXError::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            parse("import"),
+            Err(ConsulXError::MissingArgument("file"))
+        ));
+    }
+
+    #[test]
+    fn backup_and_restore() {
+        assert_eq!(
+            parse("backup app/ app.cxb").unwrap(),
+            Command::Backup { prefix: "app/".into(), file: "app.cxb".into() }
+        );
+        assert!(matches!(
+            parse("backup app/"),
+            Err(ConsulXError::MissingArgument("file"))
+        ));
+        assert_eq!(
+            parse("restore app.cxb").unwrap(),
+            Command::Restore { file: "app.cxb".into(), yes: false }
+        );
+        assert_eq!(
+            parse("restore --yes app.cxb").unwrap(),
+            Command::Restore { file: "app.cxb".into(), yes: true }
+        );
+    }
+
+    #[test]
+    fn diff_forms() {
+        assert_eq!(
+            parse("diff staging/app/ prod/app/").unwrap(),
+            Command::Diff {
+                target: DiffTarget::Prefixes { a: "staging/app/".into(), b: "prod/app/".into() },
+            }
+        );
+        assert_eq!(
+            parse("diff --dc dc1 --dc dc2 app/").unwrap(),
+            Command::Diff {
+                target: DiffTarget::Datacenters { prefix: "app/".into(), a: "dc1".into(), b: "dc2".into() },
+            }
+        );
+        assert!(matches!(
+            parse("diff app/"),
+            Err(ConsulXError::MissingArgument(_))
+        ));
+        assert!(matches!(
+            parse("diff --dc dc1 app/ other/"),
+            Err(ConsulXError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn push_and_pull_with_flags() {
+        assert_eq!(
+            parse("push ./config app/").unwrap(),
+            Command::Push { dir: "./config".into(), prefix: "app/".into(), prune: false, yes: false }
+        );
+        assert_eq!(
+            parse("pull app/ ./config --prune -y").unwrap(),
+            Command::Pull { prefix: "app/".into(), dir: "./config".into(), prune: true, yes: true }
+        );
+        assert!(matches!(
+            parse("push ./config"),
+            Err(ConsulXError::MissingArgument("prefix"))
+        ));
+        assert!(matches!(
+            parse("push ./config app/ --purge"),
+            Err(ConsulXError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn format_flags() {
+        assert_eq!(
+            parse("get --as yaml app/cfg").unwrap(),
+            Command::Get { key: "app/cfg".into(), as_format: Some(Format::Yaml) }
+        );
+        assert_eq!(
+            parse("edit --as toml app/cfg").unwrap(),
+            Command::Edit { key: "app/cfg".into(), as_format: Some(Format::Toml) }
+        );
+        assert_eq!(
+            parse("put --from yaml --cas 3 app/cfg enabled: true").unwrap(),
+            Command::Put {
+                key: "app/cfg".into(),
+                value: "enabled: true".into(),
+                cas: Some(3),
+                from: Some(Format::Yaml),
+                verbatim: false,
+            }
+        );
+        assert!(matches!(
+            parse("put --from yaml --verbatim k a: 1").unwrap(),
+            Command::Put { verbatim: true, .. }
+        ));
+        assert!(matches!(parse("get --as ini k"), Err(ConsulXError::InvalidArgument(_))));
+        assert!(matches!(parse("put --verbatim k v"), Err(ConsulXError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn json_field_commands() {
+        assert_eq!(
+            parse("get-json app/cfg").unwrap(),
+            Command::GetJson { key: "app/cfg".into(), path: None }
+        );
+        assert_eq!(
+            parse("get-json app/cfg '$.db.hosts[0]'").unwrap(),
+            Command::GetJson { key: "app/cfg".into(), path: Some("$.db.hosts[0]".into()) }
+        );
+        assert_eq!(
+            parse(r#"set-json app/cfg $.db {"host": "a b"}"#).unwrap(),
+            Command::SetJson { key: "app/cfg".into(), path: "$.db".into(), json: r#"{"host": "a b"}"#.into() }
+        );
+        assert_eq!(
+            parse("unset-json app/cfg /feature/beta").unwrap(),
+            Command::UnsetJson { key: "app/cfg".into(), path: "/feature/beta".into() }
+        );
+        assert!(matches!(
+            parse("set-json app/cfg $.enabled"),
+            Err(ConsulXError::MissingArgument("json"))
+        ));
+    }
+
+    #[test]
+    fn patch_modes() {
+        assert_eq!(
+            parse(r#"patch app/cfg {"db": {"port": 5433}}"#).unwrap(),
+            Command::Patch { key: "app/cfg".into(), patch: r#"{"db": {"port": 5433}}"#.into(), json_patch: false }
+        );
+        assert_eq!(
+            parse(r#"patch --json-patch app/cfg [{"op":"remove","path":"/a"}]"#).unwrap(),
+            Command::Patch { key: "app/cfg".into(), patch: r#"[{"op":"remove","path":"/a"}]"#.into(), json_patch: true }
+        );
+        assert!(matches!(
+            parse("patch --json-patch app/cfg"),
+            Err(ConsulXError::MissingArgument("patch"))
+        ));
+    }
+
+    #[test]
+    fn recursive_delete() {
+        assert_eq!(
+            parse("del -r services/old/").unwrap(),
+            Command::DeleteTree { prefix: "services/old/".into(), yes: false }
+        );
+        assert_eq!(
+            parse("delete --yes --recursive svc").unwrap(),
+            Command::DeleteTree { prefix: "svc".into(), yes: true }
+        );
+        for input in ["del -r", "del -r \"\"", "del -r /"] {
+            assert!(
+                matches!(parse(input), Err(ConsulXError::MissingArgument("prefix"))),
+                "{input}"
+            );
+        }
+        assert!(matches!(
+            parse("del -r --cas 3 svc"),
+            Err(ConsulXError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn grep_accepts_quoted_patterns() {
+        assert_eq!(
+            parse("grep -i 'db old' app/").unwrap(),
+            Command::Grep { pattern: "db old".into(), prefix: "app/".into(), ignore_case: true }
+        );
+        assert_eq!(
+            parse(r"grep db-old\.internal").unwrap(),
+            Command::Grep { pattern: r"db-old\.internal".into(), prefix: "".into(), ignore_case: false }
+        );
+        assert!(matches!(parse("grep"), Err(ConsulXError::MissingArgument("pattern"))));
+        assert!(matches!(parse("grep 'open"), Err(ConsulXError::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn find_parses_filters() {
+        assert_eq!(
+            parse("find svc/ --key-regex '/config$' --json-path '$.enabled' --eq true").unwrap(),
+            Command::Find {
+                prefix: "svc/".into(),
+                key_regex: Some("/config$".into()),
+                json_path: Some("$.enabled".into()),
+                eq: Some("true".into()),
+            }
+        );
+        assert!(matches!(
+            parse("find svc/ --eq"),
+            Err(ConsulXError::MissingArgument("flag value"))
+        ));
+        assert!(matches!(
+            parse("find svc/ --value x"),
+            Err(ConsulXError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn copy_and_move_commands() {
+        assert_eq!(
+            parse("cp app/a app/b").unwrap(),
+            Command::Copy { src: "app/a".into(), dst: "app/b".into(), recursive: false }
+        );
+        assert_eq!(
+            parse("mv -r app/v1/ app/v2/").unwrap(),
+            Command::Move { src: "app/v1/".into(), dst: "app/v2/".into(), recursive: true }
+        );
+        assert_eq!(
+            parse("rename old new").unwrap(),
+            Command::Move { src: "old".into(), dst: "new".into(), recursive: false }
+        );
+        assert!(matches!(
+            parse("cp app/a"),
+            Err(ConsulXError::MissingArgument("dst"))
+        ));
+        assert!(matches!(
+            parse("cp -R a b"),
+            Err(ConsulXError::InvalidArgument(_))
+        ));
+    }
+
+    #[test]
+    fn transaction_commands() {
+        assert_eq!(parse("begin").unwrap(), Command::Begin);
+        assert_eq!(parse("COMMIT").unwrap(), Command::Commit);
+        assert_eq!(parse("abort").unwrap(), Command::Abort);
+    }
+
+    #[test]
+    fn lock_commands() {
+        assert_eq!(parse("lock jobs/leader").unwrap(), Command::Lock { key: "jobs/leader".into() });
+        assert_eq!(parse("unlock jobs/leader").unwrap(), Command::Unlock { key: "jobs/leader".into() });
+        assert_eq!(parse("sessions").unwrap(), Command::Sessions);
+        assert!(matches!(
+            parse("lock"),
+            Err(ConsulXError::MissingArgument("key"))
+        ));
+    }
 }
@@ -1,10 +1,13 @@
 use reedline::{DefaultPrompt, DefaultCompleter, Reedline, Signal};
 use colored::Colorize;
+use std::collections::HashMap;
 
 use crate::client::ConsulXClient;
-use crate::commands::{kv, watch};
+use crate::commands::{self, kv, session, txn};
 use crate::errors::{ConsulXError, Result};
+use crate::lock::ConsulLock;
 use crate::parser::{parse, Command};
+use crate::txn::Txn;
 
 pub async fn start_repl(client: ConsulXClient) -> Result<()> {
     let client = std::sync::Arc::new(client);
@@ -12,6 +15,7 @@ pub async fn start_repl(client: ConsulXClient) -> Result<()> {
     // Basic command completion
     let commands = vec![
         "get".into(),
+        "stat".into(),
         "put".into(),
         "del".into(),
         "delete".into(),
@@ -21,7 +25,28 @@ pub async fn start_repl(client: ConsulXClient) -> Result<()> {
         "tree".into(),
         "get-json".into(),
         "put-json".into(),
+        "set-json".into(),
+        "unset-json".into(),
+        "patch".into(),
         "edit".into(),
+        "export".into(),
+        "import".into(),
+        "backup".into(),
+        "restore".into(),
+        "diff".into(),
+        "push".into(),
+        "pull".into(),
+        "grep".into(),
+        "find".into(),
+        "cp".into(),
+        "mv".into(),
+        "rename".into(),
+        "begin".into(),
+        "commit".into(),
+        "abort".into(),
+        "lock".into(),
+        "unlock".into(),
+        "sessions".into(),
         "help".into(),
         "exit".into(),
         "quit".into(),
@@ -35,6 +60,8 @@ pub async fn start_repl(client: ConsulXClient) -> Result<()> {
     println!("{}", "Welcome to consulx REPL".bold());
     println!("Type 'help' for commands. 'exit' or 'quit' to leave.");
 
+    let mut state = ReplState::default();
+
     loop {
         match line_editor.read_line(&prompt) {
             Ok(Signal::Success(input)) => {
@@ -45,28 +72,22 @@ pub async fn start_repl(client: ConsulXClient) -> Result<()> {
                     break;
                 }
 
-                match parse(trimmed) {
-                    Ok(Command::Empty) => {}
-                    Ok(Command::Help) => print_help(),
-                    Ok(Command::Get { key }) => kv::cmd_get(&client, &key).await?,
-                    Ok(Command::Put { key, value }) => kv::cmd_put(&client, &key, &value).await?,
-                    Ok(Command::Delete { key }) => kv::cmd_delete(&client, &key).await?,
-                    Ok(Command::List { prefix }) => kv::cmd_list(&client, &prefix).await?,
-                    Ok(Command::Watch { key }) => watch::cmd_watch_key(&client, &key).await?,
-                    Ok(Command::WatchPrefix { prefix }) => {
-                        watch::cmd_watch_prefix(&client, &prefix).await?
-                    }
-                    Ok(Command::Tree { prefix }) => kv::cmd_tree(&client, &prefix).await?,
-                    Ok(Command::GetJson { key }) => kv::cmd_get_json(&client, &key).await?,
-                    Ok(Command::PutJson { key, json }) => {
-                        kv::cmd_put_json(&client, &key, &json).await?
-                    }
-                    Ok(Command::Edit { key }) => kv::cmd_edit(&client, &key).await?,
+                let result = match parse(trimmed) {
+                    Ok(cmd) => state.run(&client, cmd).await,
+                    Err(e) => Err(e),
+                };
+
+                match result {
+                    Ok(()) => {}
+                    Err(ConsulXError::NotFound(_)) => println!("<nil>"),
                     Err(ConsulXError::UnknownCommand(cmd)) => {
                         eprintln!("{} {}", "Unknown command:".red(), cmd);
                     }
                     Err(e) => {
                         eprintln!("{} {}", "Error:".red(), e);
+                        if let Some(hint) = e.hint() {
+                            eprintln!("{} {hint}", "Hint:".yellow());
+                        }
                     }
                 }
             }
@@ -81,21 +102,111 @@ pub async fn start_repl(client: ConsulXClient) -> Result<()> {
         }
     }
 
+    state.release_locks().await;
+
     Ok(())
 }
 
-fn print_help() {
+/// State that lives across REPL lines: an open transaction and held locks.
+#[derive(Default)]
+pub(crate) struct ReplState {
+    /// Open transaction, if any: while set, put/del/del -r are staged instead of run.
+    pending: Option<Txn>,
+    /// Locks taken with `lock`, held (and renewed) until `unlock` or exit.
+    locks: HashMap<String, ConsulLock>,
+}
+
+impl ReplState {
+    pub(crate) async fn run(&mut self, client: &ConsulXClient, cmd: Command) -> Result<()> {
+        match cmd {
+            Command::Put { key, value, cas, from, verbatim } if self.pending.is_some() => {
+                let value = kv::encode_put_value(&value, from, verbatim)?;
+                txn::stage_put(self.pending.as_mut().unwrap(), &key, &value, cas);
+                Ok(())
+            }
+            Command::Delete { key, cas } if self.pending.is_some() => {
+                txn::stage_delete(self.pending.as_mut().unwrap(), &key, cas);
+                Ok(())
+            }
+            Command::DeleteTree { prefix, .. } if self.pending.is_some() => {
+                txn::stage_delete_tree(self.pending.as_mut().unwrap(), &prefix)
+            }
+            Command::Begin => {
+                if self.pending.is_some() {
+                    return Err(anyhow::anyhow!("transaction already open").into());
+                }
+                self.pending = Some(Txn::new());
+                println!("BEGIN (put/del/del -r are queued until commit)");
+                Ok(())
+            }
+            Command::Commit => match self.pending.take() {
+                Some(t) => txn::cmd_commit(client, &t).await,
+                None => Err(anyhow::anyhow!("no open transaction").into()),
+            },
+            Command::Abort => match self.pending.take() {
+                Some(t) => {
+                    println!("ABORTED ({} ops discarded)", t.len());
+                    Ok(())
+                }
+                None => Err(anyhow::anyhow!("no open transaction").into()),
+            },
+            Command::Lock { key } => session::cmd_lock(client, &mut self.locks, &key).await,
+            Command::Unlock { key } => session::cmd_unlock(&mut self.locks, &key).await,
+            other => commands::execute(client, other).await,
+        }
+    }
+
+    /// Ops staged by a `begin` that was never committed or aborted.
+    pub(crate) fn discard_pending(&mut self) -> Option<usize> {
+        self.pending.take().map(|t| t.len())
+    }
+
+    /// Release explicitly: the runtime may shut down before Drop's cleanup runs.
+    pub(crate) async fn release_locks(&mut self) {
+        for (key, lock) in self.locks.drain() {
+            if let Err(e) = lock.release().await {
+                eprintln!("{} releasing '{key}': {e}", "Error:".red());
+            }
+        }
+    }
+}
+
+pub fn print_help() {
     println!("{}", "Commands:".bold());
-    println!("  get <key>");
-    println!("  put <key> <value>");
-    println!("  del|delete <key>");
+    println!("  get [--as json|yaml|toml|hcl] <key>  # raw value, or JSON converted");
+    println!("  stat <key> | get -v <key>  # value with index/flags/session");
+    println!("  put [--cas <index>] [--from <format> [--verbatim]] <key> <value>");
+    println!("  del|delete [--cas <index>] <key>");
+    println!("  del -r <prefix> [--yes]  # preview, confirm by retyping, delete the subtree");
     println!("  list <prefix>");
     println!("  watch <key>");
     println!("  watch-prefix <prefix>");
     println!("  tree <prefix>             # ASCII tree view");
-    println!("  get-json <key>           # pretty-print JSON");
+    println!("  get-json <key> [path]    # pretty-print JSON, or one field ($.a.b[0] or /a/b/0)");
     println!("  put-json <key> <json>    # validate & store JSON");
-    println!("  edit <key>               # open value in $EDITOR");
+    println!("  set-json <key> <path> <json>  # change one field (CAS read-modify-write)");
+    println!("  unset-json <key> <path>  # remove one field (CAS read-modify-write)");
+    println!("  patch <key> <merge-patch>            # RFC 7386 merge patch, CAS-guarded");
+    println!("  patch --json-patch <key> <ops>       # RFC 6902 operations, CAS-guarded");
+    println!("  edit [--as <format>] <key>  # open value in $EDITOR (JSON shown as <format>)");
+    println!("  export <prefix> [file]   # consul kv export JSON (stdout if no file)");
+    println!("  import <file> [--prefix-rewrite old=new]");
+    println!("  backup <prefix> <file>   # compressed, checksummed snapshot with metadata");
+    println!("  restore <file> [--yes]   # verify, preview and restore a backup");
+    println!("  diff <prefixA> <prefixB|export.json>    # added/removed/changed keys");
+    println!("  diff --dc <dc1> --dc <dc2> <prefix>     # same prefix across datacenters");
+    println!("  push <dir> <prefix> [--prune] [--yes]   # mirror files to keys");
+    println!("  pull <prefix> <dir> [--prune] [--yes]   # materialise keys as files");
+    println!("  grep [-i] <regex> [prefix]  # search values, highlighting matching lines");
+    println!("  find [prefix] [--key-regex <re>] [--json-path <path>] [--eq <json>]");
+    println!("  cp [-r] <src> <dst>      # copy a key (or prefix with -r), keeping flags");
+    println!("  mv|rename [-r] <src> <dst>  # move a key (or prefix with -r)");
+    println!("  begin                    # start queueing put/del/del -r into a transaction");
+    println!("  commit                   # apply queued ops atomically");
+    println!("  abort                    # discard queued ops");
+    println!("  lock <key>               # take a session lock, held until unlock/exit");
+    println!("  unlock <key>             # release a lock taken with `lock`");
+    println!("  sessions                 # list Consul sessions");
     println!("  help");
     println!("  exit | quit");
 }
@@ -0,0 +1,143 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Total attempts per request (`1` disables retries).
+pub const RETRY_ATTEMPTS_ENV: &str = "CONSULX_RETRY_ATTEMPTS";
+/// Delay before the first retry; doubles on each one after that.
+pub const RETRY_BACKOFF_ENV: &str = "CONSULX_RETRY_BACKOFF";
+pub const CONNECT_TIMEOUT_ENV: &str = "CONSULX_CONNECT_TIMEOUT";
+/// Per-request timeout. Blocking queries get their wait time on top.
+pub const REQUEST_TIMEOUT_ENV: &str = "CONSULX_REQUEST_TIMEOUT";
+
+/// Whether a request can safely be sent again after an unclear failure.
+///
+/// Reads and session renew/destroy are `Safe`. Every write is `Unsafe`,
+/// CAS and lock acquire/release included: if the first try committed but
+/// the response was lost, a replay fails the check against our own write
+/// and reports a conflict that never happened. `Unsafe` requests are only
+/// retried when Consul certainly never acted on them (a failed connect or
+/// a 429).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Idempotency {
+    Safe,
+    Unsafe,
+}
+
+/// When and how often a failed request is retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total tries per request, including the first.
+    pub max_attempts: u32,
+    /// Backoff before the first retry; doubled each time, then jittered.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Response statuses worth another try.
+    pub retry_statuses: Vec<u16>,
+    /// Retry connection failures, resets and timeouts.
+    pub retry_transport: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+            retry_statuses: vec![429, 500, 502, 503, 504],
+            retry_transport: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Send every request exactly once.
+    pub fn none() -> Self {
+        Self { max_attempts: 1, ..Self::default() }
+    }
+
+    /// Should a response with `status` be retried? Only a 429 is known to
+    /// be rejected before Consul acts on it, so only that retries `Unsafe`.
+    pub fn retries_status(&self, status: u16, idempotency: Idempotency) -> bool {
+        self.retry_statuses.contains(&status) && (idempotency == Idempotency::Safe || status == 429)
+    }
+
+    /// Should a transport error be retried? A failed connect never reached
+    /// Consul; anything later (reset, timeout) might have, so it only
+    /// retries `Safe` requests.
+    pub fn retries_error(&self, error: &reqwest::Error, idempotency: Idempotency) -> bool {
+        self.retry_transport && !error.is_builder() && (error.is_connect() || idempotency == Idempotency::Safe)
+    }
+
+    /// Backoff before retry number `retry` (1-based): exponential, capped at
+    /// `max_delay`, with the upper half randomised by `jitter`.
+    pub fn delay(&self, retry: u32, jitter: u64) -> Duration {
+        let exp = self.base_delay.saturating_mul(1 << retry.saturating_sub(1).min(16));
+        let capped = exp.min(self.max_delay);
+        let half = capped / 2;
+        let spread = half.as_millis() as u64;
+        half + Duration::from_millis(if spread == 0 { 0 } else { jitter % (spread + 1) })
+    }
+
+    /// [`delay`](Self::delay) with a fresh random jitter.
+    pub(crate) fn jittered_delay(&self, retry: u32) -> Duration {
+        self.delay(retry, RandomState::new().build_hasher().finish())
+    }
+}
+
+/// Parse a duration like `500ms`, `2s` or `1m`; a bare number is seconds.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: u64 = n.parse().ok()?;
+    match unit {
+        "" | "s" => Some(Duration::from_secs(n)),
+        "ms" => Some(Duration::from_millis(n)),
+        "m" => Some(Duration::from_secs(n * 60)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            ..RetryPolicy::default()
+        };
+        // with zero jitter the delay is the lower half of the window
+        assert_eq!(policy.delay(1, 0), Duration::from_millis(50));
+        assert_eq!(policy.delay(2, 0), Duration::from_millis(100));
+        assert_eq!(policy.delay(3, 0), Duration::from_millis(200));
+        assert_eq!(policy.delay(10, 0), Duration::from_millis(500));
+        assert_eq!(policy.delay(100, 0), Duration::from_millis(500));
+        // and never beyond the full window
+        assert_eq!(policy.delay(1, 50), Duration::from_millis(100));
+        assert!(policy.delay(1, u64::MAX) <= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn unsafe_requests_only_retry_when_rejected_up_front() {
+        let policy = RetryPolicy::default();
+        assert!(policy.retries_status(503, Idempotency::Safe));
+        assert!(!policy.retries_status(503, Idempotency::Unsafe));
+        assert!(policy.retries_status(429, Idempotency::Unsafe));
+        assert!(!policy.retries_status(403, Idempotency::Safe));
+        assert!(!RetryPolicy { retry_statuses: vec![], ..policy }.retries_status(429, Idempotency::Safe));
+    }
+
+    #[test]
+    fn durations_parse_with_units() {
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("3s"), Some(Duration::from_secs(3)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("10"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_duration("1h"), None);
+        assert_eq!(parse_duration("fast"), None);
+    }
+}
@@ -0,0 +1,264 @@
+use anyhow::{Context, Result};
+use jsonschema::Validator;
+use serde_json::Value;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+
+use colored::Colorize;
+
+use crate::client::ConsulXClient;
+use crate::errors::{ClientError, ConsulXError};
+use crate::jsonpath::JsonPath;
+
+/// Env var naming the local schema config file.
+pub const SCHEMA_CONFIG_ENV: &str = "CONSULX_SCHEMAS";
+/// Local schema config used when [`SCHEMA_CONFIG_ENV`] is unset.
+pub const DEFAULT_SCHEMA_FILE: &str = ".consulx/schemas.json";
+/// Reserved Consul key holding a shared schema config.
+pub const SCHEMA_KEY: &str = "_consulx/schemas";
+
+/// One way a value fails its schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// Where in the document, e.g. `$.db.port`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+struct Rule {
+    glob: String,
+    validator: Validator,
+}
+
+/// JSON Schemas registered per key glob.
+///
+/// A config is a JSON object mapping globs to schemas, either inline or (in
+/// a local file only) as a path relative to that file:
+///
+/// ```json
+/// { "app/features/*": "flag.schema.json", "app/db": { "type": "object" } }
+/// ```
+///
+/// In globs `*` and `?` stay within one path segment; `**` also crosses `/`.
+/// Every matching rule applies.
+#[derive(Default)]
+pub struct SchemaRegistry {
+    rules: Vec<Rule>,
+}
+
+impl SchemaRegistry {
+    /// Build from a config object. String schemas are read from files under
+    /// `base_dir`; without one (a config stored in Consul) they are rejected.
+    pub fn from_config(config: &Value, base_dir: Option<&Path>) -> Result<Self> {
+        let map = config.as_object().context("schema config must be a JSON object of glob -> schema")?;
+        let mut rules = Vec::new();
+
+        for (glob, schema) in map {
+            let schema = match (schema, base_dir) {
+                (Value::String(file), Some(dir)) => {
+                    let path = dir.join(file);
+                    let text = fs::read_to_string(&path)
+                        .with_context(|| format!("reading schema {} for '{glob}'", path.display()))?;
+                    serde_json::from_str(&text)
+                        .with_context(|| format!("schema {} is not valid JSON", path.display()))?
+                }
+                (Value::String(_), None) => {
+                    anyhow::bail!("schema for '{glob}': file references are only allowed in a local config");
+                }
+                (schema, _) => schema.clone(),
+            };
+            let validator = jsonschema::validator_for(&schema)
+                .map_err(|e| anyhow::anyhow!("invalid schema for '{glob}': {e}"))?;
+            rules.push(Rule { glob: glob.clone(), validator });
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        let config: Value = serde_json::from_str(&text)
+            .with_context(|| format!("{} is not valid JSON", path.display()))?;
+        Self::from_config(&config, Some(path.parent().unwrap_or(Path::new("."))))
+    }
+
+    /// The local config (`$CONSULX_SCHEMAS`, else `.consulx/schemas.json` if
+    /// present) followed by the shared one at [`SCHEMA_KEY`], if set.
+    ///
+    /// A token that can't read [`SCHEMA_KEY`] (403) just means no shared
+    /// schemas; a warning is printed once per process.
+    pub async fn load(client: &ConsulXClient) -> Result<Self> {
+        let mut registry = match std::env::var_os(SCHEMA_CONFIG_ENV) {
+            Some(path) => Self::from_file(PathBuf::from(path))?,
+            None if Path::new(DEFAULT_SCHEMA_FILE).is_file() => Self::from_file(DEFAULT_SCHEMA_FILE)?,
+            None => Self::default(),
+        };
+
+        let shared = match client.kv_get_raw(SCHEMA_KEY).await {
+            Ok(raw) => raw,
+            Err(ClientError::NotFound(_)) => None,
+            Err(ClientError::PermissionDenied { .. }) => {
+                static WARNED: Once = Once::new();
+                WARNED.call_once(|| {
+                    eprintln!(
+                        "{} can't read shared schemas at '{SCHEMA_KEY}' (permission denied); skipping them",
+                        "Warning:".yellow()
+                    );
+                });
+                None
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(raw) = shared {
+            let config: Value = serde_json::from_str(&raw)
+                .with_context(|| format!("schema config at '{SCHEMA_KEY}' is not valid JSON"))?;
+            registry.rules.extend(Self::from_config(&config, None)?.rules);
+        }
+        Ok(registry)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// True if at least one schema governs `key`.
+    pub fn applies_to(&self, key: &str) -> bool {
+        self.rules.iter().any(|r| glob_match(&r.glob, key))
+    }
+
+    /// Every violation of every schema matching `key`; empty means valid.
+    pub fn validate(&self, key: &str, doc: &Value) -> Vec<SchemaViolation> {
+        self.rules
+            .iter()
+            .filter(|r| glob_match(&r.glob, key))
+            .flat_map(|r| r.validator.iter_errors(doc))
+            .map(|e| {
+                let pointer = e.instance_path().to_string();
+                let path = JsonPath::parse(&pointer).map(|p| p.to_string()).unwrap_or(pointer);
+                SchemaViolation { path, message: e.to_string() }
+            })
+            .collect()
+    }
+
+    /// [`validate`](Self::validate) as a `Result`, for a document about to
+    /// be written.
+    pub fn check(&self, key: &str, doc: &Value) -> std::result::Result<(), ConsulXError> {
+        let violations = self.validate(key, doc);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(ConsulXError::SchemaViolation { key: key.to_string(), violations })
+    }
+
+    /// Like [`validate`](Self::validate) for a raw value: a value under a
+    /// schema that isn't JSON at all is itself a violation.
+    pub fn validate_raw(&self, key: &str, raw: &[u8]) -> Vec<SchemaViolation> {
+        if !self.applies_to(key) {
+            return vec![];
+        }
+        match serde_json::from_slice::<Value>(raw) {
+            Ok(doc) => self.validate(key, &doc),
+            Err(e) => vec![SchemaViolation { path: "$".into(), message: format!("not valid JSON: {e}") }],
+        }
+    }
+}
+
+/// Match `key` against a glob where `*`/`?` don't cross `/` and `**` does.
+pub fn glob_match(pattern: &str, key: &str) -> bool {
+    fn go(p: &[u8], k: &[u8]) -> bool {
+        match p {
+            [] => k.is_empty(),
+            [b'*', b'*', rest @ ..] => (0..=k.len()).any(|i| go(rest, &k[i..])),
+            [b'*', rest @ ..] => {
+                let seg = k.iter().position(|c| *c == b'/').unwrap_or(k.len());
+                (0..=seg).any(|i| go(rest, &k[i..]))
+            }
+            [b'?', rest @ ..] => matches!(k, [c, ..] if *c != b'/') && go(rest, &k[1..]),
+            [c, rest @ ..] => k.first() == Some(c) && go(rest, &k[1..]),
+        }
+    }
+    go(pattern.as_bytes(), key.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn registry() -> SchemaRegistry {
+        let config = json!({
+            "app/features/*": {
+                "type": "object",
+                "properties": { "enabled": { "type": "boolean" } },
+                "required": ["enabled"]
+            }
+        });
+        SchemaRegistry::from_config(&config, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn unreadable_shared_schemas_are_skipped() {
+        let agent = crate::client::test_agent::spawn(vec![(403, "Permission denied")]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+        assert!(SchemaRegistry::load(&client).await.unwrap().is_empty());
+
+        let agent = crate::client::test_agent::spawn(vec![(500, "boom")]).await;
+        let client = crate::ConsulXClientBuilder::new(&agent.addr).retry_policy(crate::RetryPolicy::none()).build().unwrap();
+        assert!(SchemaRegistry::load(&client).await.is_err());
+    }
+
+    #[test]
+    fn globs_respect_segments() {
+        assert!(glob_match("app/features/*", "app/features/beta"));
+        assert!(!glob_match("app/features/*", "app/features/beta/x"));
+        assert!(glob_match("app/**", "app/features/beta/x"));
+        assert!(glob_match("app/**/config", "app/a/b/config"));
+        assert!(glob_match("svc/?/cfg", "svc/a/cfg"));
+        assert!(!glob_match("svc/?/cfg", "svc/ab/cfg"));
+        assert!(glob_match("exact/key", "exact/key"));
+    }
+
+    #[test]
+    fn violations_point_at_the_failing_path() {
+        let reg = registry();
+        assert!(reg.validate("app/features/beta", &json!({"enabled": true})).is_empty());
+
+        let errs = reg.validate("app/features/beta", &json!({"enabled": "yes"}));
+        assert_eq!(errs.len(), 1);
+        assert_eq!(errs[0].path, "$.enabled");
+        assert!(errs[0].message.contains("boolean"), "{}", errs[0].message);
+
+        // keys outside every glob are never checked
+        assert!(reg.validate("app/other", &json!({"enabled": "yes"})).is_empty());
+    }
+
+    #[test]
+    fn raw_values_under_a_schema_must_be_json() {
+        let reg = registry();
+        let errs = reg.validate_raw("app/features/beta", b"enabled=true");
+        assert_eq!(errs[0].path, "$");
+        assert!(reg.validate_raw("app/other", b"enabled=true").is_empty());
+    }
+
+    #[test]
+    fn file_references_resolve_relative_to_the_config() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        fs::write(dir.join("flag.schema.json"), r#"{"type":"object"}"#).unwrap();
+        fs::write(dir.join("schemas.json"), r#"{"app/*":"flag.schema.json"}"#).unwrap();
+
+        let reg = SchemaRegistry::from_file(dir.join("schemas.json")).unwrap();
+        assert_eq!(reg.validate("app/x", &json!([1])).len(), 1);
+
+        // but not from a config stored in Consul
+        assert!(SchemaRegistry::from_config(&json!({"app/*": "flag.schema.json"}), None).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize, Serializer};
+use std::time::Duration;
+
+use crate::client::{ConsulXClient, error_from};
+use crate::errors::{ClientError, ClientResult as Result};
+use crate::retry::Idempotency;
+
+/// What Consul does with locks held by a session once it is invalidated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionBehavior {
+    /// Release held locks, keeping the keys.
+    #[default]
+    Release,
+    /// Delete the keys the session held locks on.
+    Delete,
+}
+
+/// Body of `PUT /v1/session/create`. Unset fields use the agent defaults.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SessionOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node: Option<String>,
+    #[serde(rename = "TTL", skip_serializing_if = "Option::is_none", serialize_with = "ser_duration")]
+    pub ttl: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "ser_duration")]
+    pub lock_delay: Option<Duration>,
+    pub behavior: SessionBehavior,
+}
+
+/// The session TTLs Consul accepts.
+pub const MIN_SESSION_TTL: Duration = Duration::from_secs(10);
+pub const MAX_SESSION_TTL: Duration = Duration::from_secs(86400);
+
+impl SessionOptions {
+    /// Reject a TTL Consul would refuse before it reaches the agent; a zero
+    /// TTL would also make the renewal timer panic.
+    pub fn validate(&self) -> Result<()> {
+        check_ttl(self.ttl.unwrap_or(MIN_SESSION_TTL))
+    }
+}
+
+pub(crate) fn check_ttl(ttl: Duration) -> Result<()> {
+    if !(MIN_SESSION_TTL..=MAX_SESSION_TTL).contains(&ttl) {
+        return Err(ClientError::InvalidRequest(format!(
+            "session TTL must be between {}s and {}s, got {ttl:?}",
+            MIN_SESSION_TTL.as_secs(),
+            MAX_SESSION_TTL.as_secs()
+        )));
+    }
+    Ok(())
+}
+
+/// Consul expects durations as Go duration strings, e.g. `"15s"`; anything
+/// finer than a second is sent in milliseconds so it isn't truncated.
+fn ser_duration<S: Serializer>(d: &Option<Duration>, s: S) -> std::result::Result<S::Ok, S::Error> {
+    match d {
+        Some(d) if d.subsec_nanos() == 0 => s.serialize_str(&format!("{}s", d.as_secs())),
+        Some(d) => s.serialize_str(&format!("{}ms", d.as_millis())),
+        None => s.serialize_none(),
+    }
+}
+
+/// A session as reported by `/v1/session/info` and `/v1/session/list`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SessionEntry {
+    #[serde(rename = "ID")]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub node: String,
+    #[serde(rename = "TTL", default)]
+    pub ttl: String,
+    /// Lock delay in nanoseconds, as Consul reports it.
+    #[serde(default)]
+    pub lock_delay: u64,
+    pub behavior: SessionBehavior,
+    pub create_index: u64,
+    pub modify_index: u64,
+}
+
+#[derive(Deserialize)]
+struct CreatedSession {
+    #[serde(rename = "ID")]
+    id: String,
+}
+
+impl ConsulXClient {
+    /// PUT /v1/session/create — returns the new session ID.
+    pub async fn session_create(&self, opts: &SessionOptions) -> Result<String> {
+        opts.validate()?;
+        let url = self.api_url("v1/session/create", &[]);
+        let resp = self.send(self.http.put(url).json(opts), Idempotency::Unsafe).await?;
+
+        if resp.status().is_success() {
+            return Ok(resp.json::<CreatedSession>().await?.id);
+        }
+
+        Err(error_from(resp).await)
+    }
+
+    /// PUT /v1/session/renew/<id> — `None` if the session no longer exists.
+    pub async fn session_renew(&self, id: &str) -> Result<Option<SessionEntry>> {
+        let url = self.api_url(&format!("v1/session/renew/{id}"), &[]);
+        let resp = self.send(self.http.put(url), Idempotency::Safe).await?;
+
+        if resp.status().is_success() {
+            let entries = resp.json::<Vec<SessionEntry>>().await?;
+            return Ok(entries.into_iter().next());
+        }
+
+        if resp.status().as_u16() == 404 {
+            return Ok(None);
+        }
+
+        Err(error_from(resp).await)
+    }
+
+    /// PUT /v1/session/destroy/<id>
+    pub async fn session_destroy(&self, id: &str) -> Result<()> {
+        let url = self.api_url(&format!("v1/session/destroy/{id}"), &[]);
+        let resp = self.send(self.http.put(url), Idempotency::Safe).await?;
+
+        if resp.status().is_success() {
+            return Ok(());
+        }
+
+        Err(error_from(resp).await)
+    }
+
+    /// GET /v1/session/info/<id>
+    pub async fn session_info(&self, id: &str) -> Result<Option<SessionEntry>> {
+        let url = self.api_url(&format!("v1/session/info/{id}"), &[]);
+        let resp = self.send(self.http.get(url), Idempotency::Safe).await?;
+
+        if resp.status().is_success() {
+            // Unknown sessions come back as `null` or `[]`, not 404.
+            let entries = resp.json::<Option<Vec<SessionEntry>>>().await?;
+            return Ok(entries.unwrap_or_default().into_iter().next());
+        }
+
+        Err(error_from(resp).await)
+    }
+
+    /// GET /v1/session/list
+    pub async fn session_list(&self) -> Result<Vec<SessionEntry>> {
+        let url = self.api_url("v1/session/list", &[]);
+        let resp = self.send(self.http.get(url), Idempotency::Safe).await?;
+
+        if resp.status().is_success() {
+            let entries = resp.json::<Option<Vec<SessionEntry>>>().await?;
+            return Ok(entries.unwrap_or_default());
+        }
+
+        Err(error_from(resp).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn options_serialize_durations_as_go_strings() {
+        let opts = SessionOptions {
+            name: Some("worker".into()),
+            ttl: Some(Duration::from_secs(15)),
+            lock_delay: Some(Duration::from_secs(0)),
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&opts).unwrap(),
+            json!({"Name": "worker", "TTL": "15s", "LockDelay": "0s", "Behavior": "release"})
+        );
+    }
+
+    #[test]
+    fn sub_second_durations_are_sent_in_milliseconds() {
+        let opts = SessionOptions {
+            lock_delay: Some(Duration::from_millis(1500)),
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(&opts).unwrap()["LockDelay"], "1500ms");
+    }
+
+    #[test]
+    fn ttl_outside_consul_limits_is_rejected() {
+        let with_ttl = |secs| SessionOptions { ttl: Some(Duration::from_secs(secs)), ..Default::default() };
+        assert!(with_ttl(0).validate().is_err());
+        assert!(with_ttl(9).validate().is_err());
+        assert!(with_ttl(10).validate().is_ok());
+        assert!(with_ttl(86401).validate().is_err());
+        assert!(SessionOptions::default().validate().is_ok());
+    }
+
+    #[test]
+    fn default_options_only_send_behavior() {
+        let opts = SessionOptions {
+            behavior: SessionBehavior::Delete,
+            ..Default::default()
+        };
+        assert_eq!(serde_json::to_value(&opts).unwrap(), json!({"Behavior": "delete"}));
+    }
+
+    #[test]
+    fn session_entry_decodes() {
+        let body = r#"[{
+            "ID": "adf4238a-882b-9ddc-4a9d-5b6758e4159e",
+            "Name": "test-session",
+            "Node": "raja-laptop-02",
+            "LockDelay": 15000000000,
+            "Behavior": "release",
+            "TTL": "30s",
+            "NodeChecks": ["serfHealth"],
+            "CreateIndex": 1086449,
+            "ModifyIndex": 1086449
+        }]"#;
+        let entries: Vec<SessionEntry> = serde_json::from_str(body).unwrap();
+        let e = &entries[0];
+        assert_eq!(e.id, "adf4238a-882b-9ddc-4a9d-5b6758e4159e");
+        assert_eq!(e.ttl, "30s");
+        assert_eq!(e.lock_delay, 15_000_000_000);
+        assert_eq!(e.behavior, SessionBehavior::Release);
+        assert_eq!(e.create_index, 1086449);
+    }
+}
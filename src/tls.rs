@@ -0,0 +1,312 @@
+use anyhow::{Context, Result};
+use reqwest::{Certificate, ClientBuilder, Identity};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::WebPkiServerVerifier;
+use rustls::crypto::ring;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub const CACERT_ENV: &str = "CONSUL_CACERT";
+pub const CAPATH_ENV: &str = "CONSUL_CAPATH";
+pub const CLIENT_CERT_ENV: &str = "CONSUL_CLIENT_CERT";
+pub const CLIENT_KEY_ENV: &str = "CONSUL_CLIENT_KEY";
+pub const TLS_SERVER_NAME_ENV: &str = "CONSUL_TLS_SERVER_NAME";
+pub const SSL_VERIFY_ENV: &str = "CONSUL_HTTP_SSL_VERIFY";
+
+/// TLS settings for talking to an HTTPS agent, mirroring the official
+/// CLI's `CONSUL_CACERT`, `CONSUL_CAPATH`, `CONSUL_CLIENT_CERT`,
+/// `CONSUL_CLIENT_KEY`, `CONSUL_TLS_SERVER_NAME` and `CONSUL_HTTP_SSL_VERIFY`.
+///
+/// When a CA is given it replaces the built-in roots, as in the official
+/// CLI. All files are PEM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsOptions {
+    /// CA bundle to verify the agent's certificate against.
+    pub ca_cert: Option<PathBuf>,
+    /// Directory of CA certificates; every file in it is loaded.
+    pub ca_path: Option<PathBuf>,
+    /// Client certificate (and chain) for mutual TLS; needs `client_key`.
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    /// Name to verify the agent's certificate against instead of the
+    /// address host, e.g. `server.dc1.consul` when connecting by IP.
+    pub server_name: Option<String>,
+    /// `false` skips certificate verification entirely. Testing only.
+    pub verify: bool,
+}
+
+impl Default for TlsOptions {
+    fn default() -> Self {
+        Self {
+            ca_cert: None,
+            ca_path: None,
+            client_cert: None,
+            client_key: None,
+            server_name: None,
+            verify: true,
+        }
+    }
+}
+
+/// Parse a boolean the way Go's `strconv.ParseBool` does.
+pub(crate) fn parse_bool(s: &str) -> Option<bool> {
+    match s {
+        "1" | "t" | "T" | "true" | "TRUE" | "True" => Some(true),
+        "0" | "f" | "F" | "false" | "FALSE" | "False" => Some(false),
+        _ => None,
+    }
+}
+
+impl TlsOptions {
+    /// Read the `CONSUL_*` TLS variables.
+    pub fn from_env() -> Result<Self> {
+        Self::from_lookup(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+    }
+
+    fn from_lookup(get: impl Fn(&str) -> Option<String>) -> Result<Self> {
+        let verify = match get(SSL_VERIFY_ENV) {
+            Some(v) => parse_bool(&v).with_context(|| format!("{SSL_VERIFY_ENV}: expected true or false, got '{v}'"))?,
+            None => true,
+        };
+        Ok(Self {
+            ca_cert: get(CACERT_ENV).map(PathBuf::from),
+            ca_path: get(CAPATH_ENV).map(PathBuf::from),
+            client_cert: get(CLIENT_CERT_ENV).map(PathBuf::from),
+            client_key: get(CLIENT_KEY_ENV).map(PathBuf::from),
+            server_name: get(TLS_SERVER_NAME_ENV),
+            verify,
+        })
+    }
+
+    /// `ca_cert`, then every file in `ca_path`.
+    fn ca_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        if let Some(file) = &self.ca_cert {
+            files.push(file.clone());
+        }
+        if let Some(dir) = &self.ca_path {
+            let entries = fs::read_dir(dir).with_context(|| format!("reading CA directory {}", dir.display()))?;
+            let mut in_dir: Vec<PathBuf> = entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_file())
+                .collect();
+            in_dir.sort();
+            files.extend(in_dir);
+        }
+        Ok(files)
+    }
+
+    /// Every CA certificate from `ca_cert` and `ca_path`.
+    fn root_certs(&self) -> Result<Vec<Certificate>> {
+        let mut certs = Vec::new();
+        for file in self.ca_files()? {
+            let found = Certificate::from_pem_bundle(&read(&file)?)
+                .with_context(|| format!("{} is not a PEM certificate bundle", file.display()))?;
+            if found.is_empty() {
+                anyhow::bail!("no certificates found in {}", file.display());
+            }
+            certs.extend(found);
+        }
+        Ok(certs)
+    }
+
+    /// Configure `http` with these settings.
+    pub(crate) fn apply(&self, mut http: ClientBuilder) -> Result<ClientBuilder> {
+        // reqwest can only check a certificate against the URL host, so a
+        // different name needs a rustls config of our own.
+        if let Some(name) = &self.server_name
+            && self.verify
+        {
+            return Ok(http.use_preconfigured_tls(self.rustls_config(name)?));
+        }
+
+        let roots = self.root_certs()?;
+        if !roots.is_empty() {
+            http = http.tls_built_in_root_certs(false);
+            for cert in roots {
+                http = http.add_root_certificate(cert);
+            }
+        }
+
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                // rustls wants the chain and the key in one PEM buffer
+                let mut pem = read(cert)?;
+                pem.push(b'\n');
+                pem.extend(read(key)?);
+                let identity = Identity::from_pem(&pem).with_context(|| {
+                    format!("invalid client certificate {} or key {}", cert.display(), key.display())
+                })?;
+                http = http.identity(identity);
+            }
+            (None, None) => {}
+            _ => anyhow::bail!("{CLIENT_CERT_ENV} and {CLIENT_KEY_ENV} must be set together"),
+        }
+
+        if !self.verify {
+            http = http.danger_accept_invalid_certs(true);
+        }
+        Ok(http)
+    }
+
+    /// The same settings as a rustls config whose certificate check uses
+    /// `server_name` instead of the address host.
+    fn rustls_config(&self, server_name: &str) -> Result<ClientConfig> {
+        let name = ServerName::try_from(server_name.to_string())
+            .with_context(|| format!("invalid {TLS_SERVER_NAME_ENV} '{server_name}'"))?;
+
+        let mut roots = RootCertStore::empty();
+        let files = self.ca_files()?;
+        if files.is_empty() {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+        for file in files {
+            let certs = CertificateDer::pem_slice_iter(&read(&file)?)
+                .collect::<Result<Vec<_>, _>>()
+                .with_context(|| format!("{} is not a PEM certificate bundle", file.display()))?;
+            if certs.is_empty() {
+                anyhow::bail!("no certificates found in {}", file.display());
+            }
+            for cert in certs {
+                roots.add(cert).with_context(|| format!("unusable CA certificate in {}", file.display()))?;
+            }
+        }
+
+        let provider = Arc::new(ring::default_provider());
+        let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider.clone()).build()?;
+        let builder = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NamedVerifier { name, inner }));
+
+        Ok(match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                let chain = CertificateDer::pem_slice_iter(&read(cert)?)
+                    .collect::<Result<Vec<_>, _>>()
+                    .with_context(|| format!("invalid client certificate {}", cert.display()))?;
+                let key_der = PrivateKeyDer::from_pem_slice(&read(key)?)
+                    .with_context(|| format!("invalid client key {}", key.display()))?;
+                builder.with_client_auth_cert(chain, key_der).with_context(|| {
+                    format!("invalid client certificate {} or key {}", cert.display(), key.display())
+                })?
+            }
+            (None, None) => builder.with_no_client_auth(),
+            _ => anyhow::bail!("{CLIENT_CERT_ENV} and {CLIENT_KEY_ENV} must be set together"),
+        })
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("reading {}", path.display()))
+}
+
+/// Full WebPKI verification, but against a fixed name rather than the
+/// host being connected to.
+#[derive(Debug)]
+struct NamedVerifier {
+    name: ServerName<'static>,
+    inner: Arc<WebPkiServerVerifier>,
+}
+
+impl ServerCertVerifier for NamedVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.inner.verify_server_cert(end_entity, intermediates, &self.name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    #[test]
+    fn env_vars_map_to_options() {
+        let opts = TlsOptions::from_lookup(lookup(&[
+            (CACERT_ENV, "/etc/consul/ca.pem"),
+            (CLIENT_CERT_ENV, "/etc/consul/cli.pem"),
+            (CLIENT_KEY_ENV, "/etc/consul/cli-key.pem"),
+            (TLS_SERVER_NAME_ENV, "server.dc1.consul"),
+            (SSL_VERIFY_ENV, "false"),
+        ]))
+        .unwrap();
+        assert_eq!(opts.ca_cert.as_deref(), Some(Path::new("/etc/consul/ca.pem")));
+        assert_eq!(opts.client_key.as_deref(), Some(Path::new("/etc/consul/cli-key.pem")));
+        assert_eq!(opts.server_name.as_deref(), Some("server.dc1.consul"));
+        assert!(!opts.verify);
+
+        assert_eq!(TlsOptions::from_lookup(lookup(&[])).unwrap(), TlsOptions::default());
+        assert!(TlsOptions::from_lookup(lookup(&[(SSL_VERIFY_ENV, "nope")])).is_err());
+    }
+
+    #[test]
+    fn client_cert_needs_a_key() {
+        let opts = TlsOptions { client_cert: Some("cert.pem".into()), ..TlsOptions::default() };
+        let err = opts.apply(reqwest::Client::builder()).err().unwrap();
+        assert!(err.to_string().contains("must be set together"), "{err}");
+    }
+
+    #[test]
+    fn non_pem_ca_files_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("readme.txt"), "not a certificate").unwrap();
+        let opts = TlsOptions { ca_path: Some(dir.path().into()), ..TlsOptions::default() };
+        let err = opts.apply(reqwest::Client::builder()).err().unwrap();
+        assert!(err.to_string().contains("readme.txt"), "{err}");
+    }
+
+    #[test]
+    fn server_name_builds_a_rustls_config() {
+        let opts = TlsOptions { server_name: Some("server.dc1.consul".into()), ..TlsOptions::default() };
+        assert!(opts.rustls_config("server.dc1.consul").is_ok());
+        assert!(opts.rustls_config("not a name!").is_err());
+
+        let half = TlsOptions { client_key: Some("key.pem".into()), ..opts };
+        let err = half.apply(reqwest::Client::builder()).err().unwrap();
+        assert!(err.to_string().contains("must be set together"), "{err}");
+    }
+
+    #[test]
+    fn go_style_booleans() {
+        assert_eq!(parse_bool("T"), Some(true));
+        assert_eq!(parse_bool("0"), Some(false));
+        assert_eq!(parse_bool("yes"), None);
+    }
+}
@@ -0,0 +1,224 @@
+use crate::client::{ConsulXClient, KvPair};
+use crate::errors::{ClientError, ClientResult as Result};
+use crate::txn::{KvOp, MAX_TXN_OPS, Txn, TxnOutcome};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferMode {
+    Copy,
+    /// Copy, then delete the source (only if it is unchanged since it was read).
+    Move,
+}
+
+impl TransferMode {
+    fn ops_per_key(self) -> usize {
+        match self {
+            TransferMode::Copy => 1,
+            TransferMode::Move => 2,
+        }
+    }
+}
+
+/// A batch that Consul rolled back; none of its keys were transferred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferFailure {
+    /// Source keys in the failed batch.
+    pub keys: Vec<String>,
+    /// The source key each failing op belonged to, and Consul's reason.
+    pub errors: Vec<(String, String)>,
+}
+
+/// Outcome of a copy or move.
+#[derive(Debug, Default)]
+pub struct TransferReport {
+    /// Number of source keys found.
+    pub total: usize,
+    /// Source keys that were transferred.
+    pub done: Vec<String>,
+    /// True if everything fit in a single all-or-nothing transaction.
+    pub atomic: bool,
+    /// Set when a batch rolled back; later batches were not attempted.
+    pub failed: Option<TransferFailure>,
+}
+
+/// The source and destination actually used: for a recursive transfer both
+/// become folder prefixes (`app/v1` means `app/v1/`, not `app/v10`), and
+/// neither may contain the other.
+fn transfer_prefixes(src: &str, dst: &str, recursive: bool) -> Result<(String, String)> {
+    if !recursive {
+        if src == dst {
+            return Err(ClientError::InvalidRequest("source and destination are the same".into()));
+        }
+        return Ok((src.to_string(), dst.to_string()));
+    }
+
+    let folder = |p: &str| if p.is_empty() || p.ends_with('/') { p.to_string() } else { format!("{p}/") };
+    let (src, dst) = (folder(src), folder(dst));
+    if src.starts_with(&dst) || dst.starts_with(&src) {
+        return Err(ClientError::InvalidRequest(format!(
+            "source '{src}' and destination '{dst}' overlap"
+        )));
+    }
+    Ok((src, dst))
+}
+
+/// Where each source key lands: `dst` itself for a single key, or `dst`
+/// plus the part of the key after `src` for a prefix.
+fn target_key(pair: &KvPair, src: &str, dst: &str, recursive: bool) -> String {
+    if recursive {
+        format!("{dst}{}", pair.key.strip_prefix(src).unwrap_or(&pair.key))
+    } else {
+        dst.to_string()
+    }
+}
+
+/// Turn source pairs into txn batches. A moved key's set and delete always
+/// share a batch so no key is ever half-moved.
+fn plan_batches(pairs: &[KvPair], src: &str, dst: &str, recursive: bool, mode: TransferMode) -> Vec<(Vec<String>, Txn)> {
+    let per_batch = MAX_TXN_OPS / mode.ops_per_key();
+    pairs
+        .chunks(per_batch)
+        .map(|chunk| {
+            let mut txn = Txn::new();
+            for pair in chunk {
+                txn.push(KvOp::Set {
+                    key: target_key(pair, src, dst, recursive),
+                    value: pair.value.clone().unwrap_or_default(),
+                    flags: pair.flags,
+                });
+                if mode == TransferMode::Move {
+                    txn.delete_cas(&pair.key, pair.modify_index);
+                }
+            }
+            (chunk.iter().map(|p| p.key.clone()).collect(), txn)
+        })
+        .collect()
+}
+
+impl ConsulXClient {
+    /// Copy or move `src` to `dst`, preserving flags. With `recursive`,
+    /// both are prefixes and every key under `src` is transferred.
+    pub async fn kv_transfer(&self, src: &str, dst: &str, recursive: bool, mode: TransferMode) -> Result<TransferReport> {
+        let (src, dst) = transfer_prefixes(src, dst, recursive)?;
+
+        let pairs = if recursive {
+            self.kv_get_recurse(&src).await?
+        } else {
+            self.kv_get_entry(&src).await?.into_iter().collect()
+        };
+
+        let batches = plan_batches(&pairs, &src, &dst, recursive, mode);
+        let mut report = TransferReport {
+            total: pairs.len(),
+            atomic: batches.len() <= 1,
+            ..Default::default()
+        };
+
+        for (keys, txn) in batches {
+            match self.txn_commit(&txn).await? {
+                TxnOutcome::Committed(_) => report.done.extend(keys),
+                TxnOutcome::RolledBack(errors) => {
+                    let errors = errors
+                        .into_iter()
+                        .map(|e| {
+                            let key = keys.get(e.op_index / mode.ops_per_key()).cloned().unwrap_or_default();
+                            (key, e.what)
+                        })
+                        .collect();
+                    report.failed = Some(TransferFailure { keys, errors });
+                    break;
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    pub async fn kv_copy(&self, src: &str, dst: &str) -> Result<TransferReport> {
+        self.kv_transfer(src, dst, false, TransferMode::Copy).await
+    }
+
+    pub async fn kv_move(&self, src: &str, dst: &str) -> Result<TransferReport> {
+        self.kv_transfer(src, dst, false, TransferMode::Move).await
+    }
+
+    pub async fn kv_copy_prefix(&self, src: &str, dst: &str) -> Result<TransferReport> {
+        self.kv_transfer(src, dst, true, TransferMode::Copy).await
+    }
+
+    pub async fn kv_move_prefix(&self, src: &str, dst: &str) -> Result<TransferReport> {
+        self.kv_transfer(src, dst, true, TransferMode::Move).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_agent;
+
+    fn pair(key: &str, flags: u64, index: u64) -> KvPair {
+        KvPair {
+            key: key.into(),
+            value: Some(b"v".to_vec()),
+            create_index: index,
+            modify_index: index,
+            lock_index: 0,
+            flags,
+            session: None,
+        }
+    }
+
+    #[test]
+    fn single_key_copy_targets_dst_and_keeps_flags() {
+        let batches = plan_batches(&[pair("a", 7, 3)], "a", "b", false, TransferMode::Copy);
+        assert_eq!(batches.len(), 1);
+        assert_eq!(
+            batches[0].1.ops(),
+            &[KvOp::Set { key: "b".into(), value: b"v".to_vec(), flags: 7 }]
+        );
+    }
+
+    #[test]
+    fn prefix_move_rewrites_keys_and_deletes_with_cas() {
+        let pairs = [pair("app/v1/x", 0, 5), pair("app/v1/y/z", 0, 6)];
+        let batches = plan_batches(&pairs, "app/v1/", "app/v2/", true, TransferMode::Move);
+        let ops = batches[0].1.ops();
+        assert_eq!(ops.len(), 4);
+        assert_eq!(ops[0].key(), "app/v2/x");
+        assert_eq!(ops[1], KvOp::DeleteCas { key: "app/v1/x".into(), index: 5 });
+        assert_eq!(ops[2].key(), "app/v2/y/z");
+    }
+
+    #[test]
+    fn recursive_prefixes_are_folders_and_must_not_overlap() {
+        assert_eq!(
+            transfer_prefixes("app/v1", "app/v2", true).unwrap(),
+            ("app/v1/".to_string(), "app/v2/".to_string())
+        );
+        // a single key stays as given
+        assert_eq!(transfer_prefixes("app/v1", "app/v2", false).unwrap().0, "app/v1");
+        assert!(transfer_prefixes("app", "app/old", true).is_err());
+        assert!(transfer_prefixes("app/old/", "app", true).is_err());
+        assert!(transfer_prefixes("", "backup", true).is_err());
+        assert!(transfer_prefixes("a", "a", false).is_err());
+    }
+
+    #[test]
+    fn large_moves_split_without_separating_set_and_delete() {
+        let pairs: Vec<KvPair> = (0..40).map(|i| pair(&format!("p/{i}"), 0, i)).collect();
+        let batches = plan_batches(&pairs, "p/", "q/", true, TransferMode::Move);
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].1.len(), MAX_TXN_OPS);
+        assert_eq!(batches[1].0.len(), 8);
+        assert!(batches.iter().all(|(_, t)| t.len() % 2 == 0));
+    }
+
+    #[tokio::test]
+    async fn transfer_errors_are_typed() {
+        let agent = test_agent::spawn(vec![(403, "Permission denied")]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+
+        let err = client.kv_copy_prefix("app/v1", "app/v2").await.unwrap_err();
+        assert!(matches!(err, ClientError::PermissionDenied { status: 403, .. }), "{err:?}");
+        let err = client.kv_move_prefix("app", "app/old").await.unwrap_err();
+        assert!(matches!(err, ClientError::InvalidRequest(_)), "{err:?}");
+    }
+}
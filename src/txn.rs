@@ -0,0 +1,377 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+
+use crate::client::{ConsulXClient, KvPair, error_from};
+use crate::errors::{ClientError, ClientResult as Result};
+use crate::retry::Idempotency;
+
+/// Consul rejects transactions with more operations than this (the agent
+/// default), so callers splitting large batches should chunk by it.
+pub const MAX_TXN_OPS: usize = 64;
+
+/// Consul's default limit on a transaction's request body
+/// (`txn_max_req_len`); chunks of large values must stay under it too.
+pub const MAX_TXN_BYTES: usize = 512 * 1024;
+
+/// A single KV operation inside a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvOp {
+    Set { key: String, value: Vec<u8>, flags: u64 },
+    Cas { key: String, value: Vec<u8>, flags: u64, index: u64 },
+    Get { key: String },
+    Delete { key: String },
+    DeleteTree { prefix: String },
+    DeleteCas { key: String, index: u64 },
+    CheckIndex { key: String, index: u64 },
+    Lock { key: String, value: Vec<u8>, session: String },
+    Unlock { key: String, value: Vec<u8>, session: String },
+}
+
+impl KvOp {
+    /// The key (or prefix) this operation targets.
+    pub fn key(&self) -> &str {
+        match self {
+            KvOp::Set { key, .. }
+            | KvOp::Cas { key, .. }
+            | KvOp::Get { key }
+            | KvOp::Delete { key }
+            | KvOp::DeleteCas { key, .. }
+            | KvOp::CheckIndex { key, .. }
+            | KvOp::Lock { key, .. }
+            | KvOp::Unlock { key, .. } => key,
+            KvOp::DeleteTree { prefix } => prefix,
+        }
+    }
+
+    fn to_wire(&self) -> WireOp<'_> {
+        let mut kv = WireKv {
+            verb: "",
+            key: self.key(),
+            value: None,
+            flags: None,
+            index: None,
+            session: None,
+        };
+        match self {
+            KvOp::Set { value, flags, .. } => {
+                kv.verb = "set";
+                kv.value = Some(BASE64.encode(value));
+                kv.flags = Some(*flags);
+            }
+            KvOp::Cas { value, flags, index, .. } => {
+                kv.verb = "cas";
+                kv.value = Some(BASE64.encode(value));
+                kv.flags = Some(*flags);
+                kv.index = Some(*index);
+            }
+            KvOp::Get { .. } => kv.verb = "get",
+            KvOp::Delete { .. } => kv.verb = "delete",
+            KvOp::DeleteTree { .. } => kv.verb = "delete-tree",
+            KvOp::DeleteCas { index, .. } => {
+                kv.verb = "delete-cas";
+                kv.index = Some(*index);
+            }
+            KvOp::CheckIndex { index, .. } => {
+                kv.verb = "check-index";
+                kv.index = Some(*index);
+            }
+            KvOp::Lock { value, session, .. } => {
+                kv.verb = "lock";
+                kv.value = Some(BASE64.encode(value));
+                kv.session = Some(session);
+            }
+            KvOp::Unlock { value, session, .. } => {
+                kv.verb = "unlock";
+                kv.value = Some(BASE64.encode(value));
+                kv.session = Some(session);
+            }
+        }
+        WireOp { kv }
+    }
+}
+
+#[derive(Serialize)]
+struct WireOp<'a> {
+    #[serde(rename = "KV")]
+    kv: WireKv<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct WireKv<'a> {
+    verb: &'static str,
+    key: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    flags: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session: Option<&'a str>,
+}
+
+/// An ordered batch of KV operations applied atomically via `/v1/txn`.
+///
+/// ```no_run
+/// # async fn demo(client: consulx::ConsulXClient) -> anyhow::Result<()> {
+/// use consulx::txn::Txn;
+///
+/// let mut txn = Txn::new();
+/// txn.set("creds/user", "alice").set("creds/pass", "s3cret");
+/// client.txn_commit(&txn).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Txn {
+    ops: Vec<KvOp>,
+}
+
+impl Txn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, op: KvOp) -> &mut Self {
+        self.ops.push(op);
+        self
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Into<Vec<u8>>) -> &mut Self {
+        self.push(KvOp::Set { key: key.into(), value: value.into(), flags: 0 })
+    }
+
+    pub fn cas(&mut self, key: &str, value: impl Into<Vec<u8>>, index: u64) -> &mut Self {
+        self.push(KvOp::Cas { key: key.into(), value: value.into(), flags: 0, index })
+    }
+
+    pub fn get(&mut self, key: &str) -> &mut Self {
+        self.push(KvOp::Get { key: key.into() })
+    }
+
+    pub fn delete(&mut self, key: &str) -> &mut Self {
+        self.push(KvOp::Delete { key: key.into() })
+    }
+
+    pub fn delete_tree(&mut self, prefix: &str) -> &mut Self {
+        self.push(KvOp::DeleteTree { prefix: prefix.into() })
+    }
+
+    pub fn delete_cas(&mut self, key: &str, index: u64) -> &mut Self {
+        self.push(KvOp::DeleteCas { key: key.into(), index })
+    }
+
+    pub fn check_index(&mut self, key: &str, index: u64) -> &mut Self {
+        self.push(KvOp::CheckIndex { key: key.into(), index })
+    }
+
+    pub fn lock(&mut self, key: &str, value: impl Into<Vec<u8>>, session: &str) -> &mut Self {
+        self.push(KvOp::Lock { key: key.into(), value: value.into(), session: session.into() })
+    }
+
+    pub fn unlock(&mut self, key: &str, value: impl Into<Vec<u8>>, session: &str) -> &mut Self {
+        self.push(KvOp::Unlock { key: key.into(), value: value.into(), session: session.into() })
+    }
+
+    pub fn ops(&self) -> &[KvOp] {
+        &self.ops
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// `Safe` to resend only if every op is a read. A replayed write, even
+    /// a conditional one, can fail its own check after the first try
+    /// committed, reporting a rollback for a transaction that applied.
+    pub fn idempotency(&self) -> Idempotency {
+        let writes = self
+            .ops
+            .iter()
+            .any(|op| !matches!(op, KvOp::Get { .. } | KvOp::CheckIndex { .. }));
+        if writes { Idempotency::Unsafe } else { Idempotency::Safe }
+    }
+
+    fn to_body(&self) -> Result<String> {
+        let wire: Vec<WireOp<'_>> = self.ops.iter().map(KvOp::to_wire).collect();
+        Ok(serde_json::to_string(&wire)?)
+    }
+}
+
+/// Why Consul rolled back a transaction, pointing at the offending op.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct TxnError {
+    pub op_index: usize,
+    pub what: String,
+}
+
+/// What happened to a submitted transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxnOutcome {
+    /// Every op applied; holds the entries returned by ops that produce one
+    /// (`get`, `set`, `cas`, ...). Values are only populated for `get`.
+    Committed(Vec<KvPair>),
+    /// Nothing was applied.
+    RolledBack(Vec<TxnError>),
+}
+
+impl TxnOutcome {
+    pub fn is_committed(&self) -> bool {
+        matches!(self, TxnOutcome::Committed(_))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct WireResponse {
+    #[serde(default)]
+    results: Option<Vec<WireResult>>,
+    #[serde(default)]
+    errors: Option<Vec<TxnError>>,
+}
+
+#[derive(Deserialize)]
+struct WireResult {
+    #[serde(rename = "KV")]
+    kv: Option<KvPair>,
+}
+
+/// Interpret a `/v1/txn` reply: 200 is a commit, 409 a rollback with
+/// per-op errors, anything else a failed request.
+fn decode_response(status: u16, body: &str) -> Result<TxnOutcome> {
+    match status {
+        200 => {
+            let resp: WireResponse = serde_json::from_str(body)?;
+            let pairs = resp
+                .results
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|r| r.kv)
+                .collect();
+            Ok(TxnOutcome::Committed(pairs))
+        }
+        409 => {
+            let resp: WireResponse = serde_json::from_str(body)?;
+            Ok(TxnOutcome::RolledBack(resp.errors.unwrap_or_default()))
+        }
+        _ => Err(ClientError::from_status(status, body, None)),
+    }
+}
+
+impl ConsulXClient {
+    /// PUT /v1/txn — apply every op in `txn` atomically.
+    pub async fn txn_commit(&self, txn: &Txn) -> Result<TxnOutcome> {
+        let url = self.api_url("v1/txn", &[]);
+        let resp = self.send(self.http.put(url).body(txn.to_body()?), txn.idempotency()).await?;
+
+        let status = resp.status().as_u16();
+        if !matches!(status, 200 | 409) {
+            return Err(error_from(resp).await);
+        }
+        let body = resp.text().await?;
+        decode_response(status, &body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    fn body(txn: &Txn) -> Value {
+        serde_json::from_str(&txn.to_body().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn set_and_cas_encode_values_as_base64() {
+        let mut txn = Txn::new();
+        txn.set("a", "hello").cas("b", "x", 7);
+        assert_eq!(
+            body(&txn),
+            json!([
+                {"KV": {"Verb": "set", "Key": "a", "Value": "aGVsbG8=", "Flags": 0}},
+                {"KV": {"Verb": "cas", "Key": "b", "Value": "eA==", "Flags": 0, "Index": 7}},
+            ])
+        );
+    }
+
+    #[test]
+    fn verbs_without_values_omit_them() {
+        let mut txn = Txn::new();
+        txn.get("a")
+            .delete("b")
+            .delete_tree("c/")
+            .delete_cas("d", 3)
+            .check_index("e", 4);
+        assert_eq!(
+            body(&txn),
+            json!([
+                {"KV": {"Verb": "get", "Key": "a"}},
+                {"KV": {"Verb": "delete", "Key": "b"}},
+                {"KV": {"Verb": "delete-tree", "Key": "c/"}},
+                {"KV": {"Verb": "delete-cas", "Key": "d", "Index": 3}},
+                {"KV": {"Verb": "check-index", "Key": "e", "Index": 4}},
+            ])
+        );
+    }
+
+    #[test]
+    fn lock_and_unlock_carry_session() {
+        let mut txn = Txn::new();
+        txn.lock("l", "", "sess-1").unlock("l", "", "sess-1");
+        assert_eq!(
+            body(&txn),
+            json!([
+                {"KV": {"Verb": "lock", "Key": "l", "Value": "", "Session": "sess-1"}},
+                {"KV": {"Verb": "unlock", "Key": "l", "Value": "", "Session": "sess-1"}},
+            ])
+        );
+    }
+
+    #[test]
+    fn decode_committed_results() {
+        let body = r#"{"Results":[{"KV":{"LockIndex":0,"Key":"a","Flags":0,"Value":null,"CreateIndex":5,"ModifyIndex":5}}],"Errors":null}"#;
+        match decode_response(200, body).unwrap() {
+            TxnOutcome::Committed(pairs) => {
+                assert_eq!(pairs.len(), 1);
+                assert_eq!(pairs[0].key, "a");
+                assert_eq!(pairs[0].modify_index, 5);
+            }
+            other => panic!("expected commit, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decode_rolled_back_errors() {
+        let body = r#"{"Results":null,"Errors":[{"OpIndex":1,"What":"failed to set key \"b\", index is stale"}]}"#;
+        assert_eq!(
+            decode_response(409, body).unwrap(),
+            TxnOutcome::RolledBack(vec![TxnError {
+                op_index: 1,
+                what: "failed to set key \"b\", index is stale".into(),
+            }])
+        );
+    }
+
+    #[test]
+    fn only_read_only_txns_are_safe_to_retry() {
+        let mut txn = Txn::new();
+        txn.get("a").check_index("b", 4);
+        assert_eq!(txn.idempotency(), Idempotency::Safe);
+        txn.cas("c", "y", 3);
+        assert_eq!(txn.idempotency(), Idempotency::Unsafe);
+    }
+
+    #[test]
+    fn decode_other_status_is_error() {
+        let err = decode_response(413, "too many ops").unwrap_err();
+        assert!(err.to_string().contains("413"));
+    }
+}
@@ -0,0 +1,162 @@
+use serde_json::Value;
+
+use crate::client::ConsulXClient;
+use crate::errors::{ClientError, ClientResult, UpdateError};
+use crate::schema::SchemaRegistry;
+use crate::txn::{KvOp, Txn, TxnOutcome};
+
+/// How many times a read-modify-write is retried after losing a CAS race.
+pub const DEFAULT_CAS_RETRIES: u32 = 5;
+
+impl ConsulXClient {
+    /// Read the JSON document at `key`, apply `update` to it, and write it
+    /// back only if the key hasn't changed in between (CAS on ModifyIndex,
+    /// flags preserved). On a conflict the document is re-read and `update`
+    /// runs again, up to `retries` more times.
+    ///
+    /// Returns the document as written, or `None` if the key doesn't exist.
+    /// Nothing is written when `update` leaves the document unchanged. An
+    /// error from `update` comes back as [`UpdateError::Rejected`].
+    pub async fn kv_update_json<F>(&self, key: &str, retries: u32, mut update: F) -> Result<Option<Value>, UpdateError>
+    where
+        F: FnMut(&mut Value) -> anyhow::Result<()>,
+    {
+        for _ in 0..=retries {
+            let Some(pair) = self.kv_get_entry(key).await? else {
+                return Ok(None);
+            };
+            let original: Value = serde_json::from_slice(pair.value.as_deref().unwrap_or_default())
+                .map_err(|e| UpdateError::NotJson { key: key.to_string(), reason: e.to_string() })?;
+
+            let mut doc = original.clone();
+            update(&mut doc).map_err(UpdateError::Rejected)?;
+            if doc == original {
+                return Ok(Some(doc));
+            }
+
+            let mut txn = Txn::new();
+            txn.push(KvOp::Cas {
+                key: key.to_string(),
+                value: serde_json::to_vec(&doc).map_err(ClientError::from)?,
+                flags: pair.flags,
+                index: pair.modify_index,
+            });
+            match self.txn_commit(&txn).await? {
+                TxnOutcome::Committed(_) => return Ok(Some(doc)),
+                TxnOutcome::RolledBack(_) => continue,
+            }
+        }
+
+        Err(ClientError::CasConflict { key: key.to_string(), attempts: retries + 1 }.into())
+    }
+
+    /// Apply an RFC 7386 JSON Merge Patch to the document at `key`, via
+    /// [`kv_update_json`](Self::kv_update_json). Nothing is written if the
+    /// result violates a schema in `schemas`.
+    pub async fn kv_merge_patch(
+        &self,
+        key: &str,
+        patch: &Value,
+        schemas: &SchemaRegistry,
+        retries: u32,
+    ) -> Result<Option<Value>, UpdateError> {
+        self.kv_update_json(key, retries, |doc| {
+            json_patch::merge(doc, patch);
+            Ok(schemas.check(key, doc)?)
+        })
+        .await
+    }
+
+    /// Apply an RFC 6902 JSON Patch (an array of operations) to the document
+    /// at `key`, via [`kv_update_json`](Self::kv_update_json). The patch is
+    /// all-or-nothing: if any operation fails (including a `test`) or the
+    /// result violates a schema in `schemas`, nothing is written.
+    pub async fn kv_json_patch(
+        &self,
+        key: &str,
+        ops: &Value,
+        schemas: &SchemaRegistry,
+        retries: u32,
+    ) -> Result<Option<Value>, UpdateError> {
+        let ops = parse_json_patch(ops)?;
+        self.kv_update_json(key, retries, |doc| {
+            json_patch::patch(doc, &ops)?;
+            Ok(schemas.check(key, doc)?)
+        })
+        .await
+    }
+}
+
+pub(crate) fn parse_json_patch(ops: &Value) -> ClientResult<json_patch::Patch> {
+    serde_json::from_value(ops.clone()).map_err(|e| ClientError::InvalidRequest(format!("invalid JSON Patch: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::test_agent;
+    use crate::errors::ConsulXError;
+    use serde_json::json;
+
+    /// `{"enabled":true}` at `app/features/x`, ModifyIndex 5.
+    const FLAG_ENTRY: &str = r#"[{"Key":"app/features/x","Value":"eyJlbmFibGVkIjp0cnVlfQ==","Flags":0,"CreateIndex":1,"ModifyIndex":5,"LockIndex":0}]"#;
+
+    #[tokio::test]
+    async fn patches_violating_a_schema_are_not_written() {
+        let agent = test_agent::spawn(vec![(200, FLAG_ENTRY)]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+        let schemas = SchemaRegistry::from_config(
+            &json!({"app/features/*": {"properties": {"enabled": {"type": "boolean"}}}}),
+            None,
+        )
+        .unwrap();
+
+        let err = client
+            .kv_merge_patch("app/features/x", &json!({"enabled": "yes"}), &schemas, 0)
+            .await
+            .unwrap_err();
+        assert!(matches!(ConsulXError::from(err), ConsulXError::SchemaViolation { .. }));
+        assert_eq!(agent.requests(), ["GET /v1/kv/app/features/x"]);
+    }
+
+    #[test]
+    fn json_patch_ops_are_validated_up_front() {
+        assert!(parse_json_patch(&json!([{"op": "replace", "path": "/a", "value": 1}])).is_ok());
+        assert!(parse_json_patch(&json!([{"op": "frobnicate", "path": "/a"}])).is_err());
+        assert!(parse_json_patch(&json!({"op": "add"})).is_err());
+    }
+
+    #[tokio::test]
+    async fn failed_json_patch_writes_nothing() {
+        let agent = test_agent::spawn(vec![(200, FLAG_ENTRY)]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+        let ops = json!([
+            {"op": "replace", "path": "/enabled", "value": false},
+            {"op": "test", "path": "/enabled", "value": true}
+        ]);
+
+        let err = client
+            .kv_json_patch("app/features/x", &ops, &SchemaRegistry::default(), 0)
+            .await
+            .unwrap_err();
+        let UpdateError::Rejected(err) = err else { panic!("expected a rejected patch, got {err:?}") };
+        assert_eq!(err.downcast::<json_patch::PatchError>().unwrap().operation, 1);
+        assert_eq!(agent.requests(), ["GET /v1/kv/app/features/x"]);
+    }
+
+    #[tokio::test]
+    async fn losing_every_cas_race_reports_the_attempts() {
+        let stale = r#"{"Results":null,"Errors":[{"OpIndex":0,"What":"index is stale"}]}"#;
+        let agent = test_agent::spawn(vec![(200, FLAG_ENTRY), (409, stale), (200, FLAG_ENTRY), (409, stale)]).await;
+        let client = ConsulXClient::with_options(&agent.addr, None, None).unwrap();
+
+        let err = client
+            .kv_merge_patch("app/features/x", &json!({"enabled": false}), &SchemaRegistry::default(), 1)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, UpdateError::Client(ClientError::CasConflict { key, attempts: 2 }) if key == "app/features/x"),
+            "{err:?}"
+        );
+    }
+}